@@ -1,5 +1,7 @@
 use crate::display::DownloadBar;
+use crate::http::{self, header, HeaderMap, HttpClient, Request};
 use crate::utils;
+use serde::{Deserialize, Serialize};
 use std::fs;
 use std::io::{self, Read};
 use std::path::Path;
@@ -26,6 +28,78 @@ impl MimeMap {
     }
 }
 
+/// The last downloaded xml of a feed, along with the headers for making conditional requests.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct FeedCache {
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+impl FeedCache {
+    /// Loads the cached headers of a feed. Returns `None` if there's no cached xml for it.
+    pub fn load(url: &str) -> Option<Self> {
+        if !Self::xml_path(url).exists() {
+            return None;
+        }
+
+        let s = fs::read_to_string(Self::headers_path(url)).ok()?;
+        serde_json::from_str(&s).ok()
+    }
+
+    /// The cached xml of the feed.
+    pub fn xml(url: &str) -> Option<String> {
+        fs::read_to_string(Self::xml_path(url)).ok()
+    }
+
+    pub fn save(url: &str, xml: &str, headers: &HeaderMap) -> Option<()> {
+        let header = |key| {
+            headers
+                .get(key)
+                .and_then(|val| val.to_str().ok())
+                .map(ToString::to_string)
+        };
+
+        let cache = Self {
+            etag: header(header::ETAG),
+            last_modified: header(header::LAST_MODIFIED),
+        };
+
+        fs::write(Self::xml_path(url), xml).ok()?;
+        fs::write(Self::headers_path(url), serde_json::to_string(&cache).ok()?).ok()?;
+        Some(())
+    }
+
+    /// Adds the `If-None-Match` and `If-Modified-Since` headers to the request.
+    pub fn conditional_request(&self, request: Request) -> Request {
+        let mut request = request;
+
+        if let Some(etag) = &self.etag {
+            request = request.header(header::IF_NONE_MATCH, etag);
+        }
+
+        if let Some(last_modified) = &self.last_modified {
+            request = request.header(header::IF_MODIFIED_SINCE, last_modified);
+        }
+
+        request
+    }
+
+    fn dir() -> PathBuf {
+        let path = utils::cache_dir().join("feeds");
+        // Failing to write to the cache is only warned about.
+        let _ = utils::create_dir(&path);
+        path
+    }
+
+    fn xml_path(url: &str) -> PathBuf {
+        Self::dir().join(format!("{}.xml", hashed_url(url)))
+    }
+
+    fn headers_path(url: &str) -> PathBuf {
+        Self::dir().join(format!("{}.json", hashed_url(url)))
+    }
+}
+
 fn read_file_to_vec(path: &Path) -> io::Result<Vec<u8>> {
     let mut file = fs::File::open(path)?;
     let mut data = Vec::new();
@@ -55,11 +129,11 @@ fn cached_image(url: &str, ui: &DownloadBar) -> Option<Vec<u8>> {
     image
 }
 
-async fn write_image(url: &str, ui: &DownloadBar) -> Option<()> {
+async fn write_image(client: &dyn HttpClient, url: &str, ui: &DownloadBar) -> Option<()> {
     use std::io::Write;
 
     let hashed = hashed_url(url);
-    let response = match reqwest::get(url).await {
+    let response = match http::get(client, url).await {
         Ok(res) => {
             ui.log_info("connected to image url");
             res
@@ -71,14 +145,14 @@ async fn write_image(url: &str, ui: &DownloadBar) -> Option<()> {
         }
     };
 
-    if response.status().is_success() {
+    if response.status.is_success() {
         let mime_type = response
-            .headers()
-            .get(reqwest::header::CONTENT_TYPE)
+            .headers
+            .get(header::CONTENT_TYPE)
             .and_then(|value| value.to_str().ok())
             .unwrap_or("")
             .to_string();
-        let data = response.bytes().await.ok()?.to_vec();
+        let data = response.bytes().await.ok()?;
         let path = utils::cache_dir().join(&hashed);
         let mut file = fs::File::create(&path).ok()?;
         file.write_all(&data).ok()?;
@@ -90,6 +164,7 @@ async fn write_image(url: &str, ui: &DownloadBar) -> Option<()> {
 }
 
 pub async fn get_image(
+    client: &dyn HttpClient,
     url: &str,
     picture_type: id3::frame::PictureType,
     ui: &DownloadBar,
@@ -97,7 +172,7 @@ pub async fn get_image(
     let data = match cached_image(url, ui) {
         Some(data) => data,
         None => {
-            write_image(url, ui).await?;
+            write_image(client, url, ui).await?;
             cached_image(url, ui)?
         }
     };
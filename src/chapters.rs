@@ -0,0 +1,134 @@
+use crate::cache;
+use crate::display::DownloadBar;
+use crate::http::{self, HttpClient};
+use serde::Deserialize;
+
+/// A single chapter from a podcasting 2.0 JSON chapters file.
+///
+/// See: <https://github.com/Podcastindex-org/podcast-namespace/blob/main/chapters/jsonChapters.md>
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+struct Chapter {
+    start_time: f64,
+    end_time: Option<f64>,
+    title: Option<String>,
+    img: Option<String>,
+    /// Chapters with `toc = false` should not be shown in the table of contents.
+    toc: Option<bool>,
+}
+
+/// The chapters of an episode, sorted by their start time.
+#[derive(Deserialize, Debug, Clone)]
+pub struct Chapters {
+    chapters: Vec<Chapter>,
+}
+
+impl Chapters {
+    /// Downloads and parses the JSON chapters file of an episode.
+    pub async fn fetch(client: &dyn HttpClient, url: &str, ui: &DownloadBar) -> Option<Self> {
+        ui.log_debug(format!("fetching chapters from: {}", url));
+
+        let response = match http::get(client, url).await {
+            Ok(res) => res,
+            Err(e) => {
+                ui.log_warn(format!("failed to connect to chapters url: {:?}", e));
+                return None;
+            }
+        };
+
+        let text = match response.text().await {
+            Ok(text) => text,
+            Err(e) => {
+                ui.log_warn(format!("failed to download chapters file: {:?}", e));
+                return None;
+            }
+        };
+
+        let mut chapters: Self = match serde_json::from_str(&text) {
+            Ok(chapters) => chapters,
+            Err(e) => {
+                ui.log_warn(format!("failed to parse chapters file: {:?}", e));
+                return None;
+            }
+        };
+
+        chapters
+            .chapters
+            .sort_by(|a, b| a.start_time.total_cmp(&b.start_time));
+
+        Some(chapters)
+    }
+
+    fn element_id(index: usize) -> String {
+        format!("chp{}", index)
+    }
+
+    /// Creates the `CHAP` frames and the `CTOC` frame referencing them.
+    ///
+    /// The end of a chapter is its `endTime` if present, otherwise the start of the next chapter.
+    /// For the last chapter we fall back to the duration of the episode, if known.
+    pub async fn into_frames(
+        self,
+        client: &dyn HttpClient,
+        duration_ms: Option<u32>,
+        ui: &DownloadBar,
+    ) -> Vec<id3::frame::Frame> {
+        use id3::frame::{Chapter as ChapterFrame, Content, Frame, TableOfContents};
+
+        let secs_to_ms = |secs: f64| (secs * 1000.0) as u32;
+
+        let mut frames = vec![];
+        let mut toc_elements = vec![];
+
+        for (index, chapter) in self.chapters.iter().enumerate() {
+            let element_id = Self::element_id(index);
+            let start_time = secs_to_ms(chapter.start_time);
+            let end_time = chapter
+                .end_time
+                .or_else(|| self.chapters.get(index + 1).map(|next| next.start_time))
+                .map(secs_to_ms)
+                .or(duration_ms)
+                .unwrap_or(start_time);
+
+            let mut sub_frames = vec![];
+
+            if let Some(title) = &chapter.title {
+                sub_frames.push(Frame::text("TIT2", title));
+            }
+
+            if let Some(img) = &chapter.img {
+                match cache::get_image(client, img, id3::frame::PictureType::Other, ui).await {
+                    Some(frame) => sub_frames.push(frame),
+                    None => ui.log_warn(format!("failed to fetch chapter image: {}", img)),
+                }
+            }
+
+            if chapter.toc.unwrap_or(true) {
+                toc_elements.push(element_id.clone());
+            }
+
+            let chapter = ChapterFrame {
+                element_id,
+                start_time,
+                end_time,
+                start_offset: u32::MAX,
+                end_offset: u32::MAX,
+                frames: sub_frames,
+            };
+
+            frames.push(Frame::with_content("CHAP", Content::Chapter(chapter)));
+        }
+
+        let toc = TableOfContents {
+            element_id: "toc".to_string(),
+            top_level: true,
+            ordered: true,
+            elements: toc_elements,
+            frames: vec![],
+        };
+
+        frames.push(Frame::with_content("CTOC", Content::TableOfContents(toc)));
+
+        frames
+    }
+}
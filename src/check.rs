@@ -0,0 +1,198 @@
+use crate::config::GlobalConfig;
+use crate::config::PodcastConfigs;
+use crate::error::Error;
+use crate::patterns::FullPattern;
+use crate::throttle;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+/// A problem with a setting in one of the config files.
+#[derive(Debug)]
+pub struct Diagnostic {
+    file: &'static str,
+    podcast: Option<String>,
+    field: String,
+    msg: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.podcast {
+            Some(podcast) => write!(
+                f,
+                "{}: [{}] {}: {}",
+                self.file, podcast, self.field, self.msg
+            ),
+            None => write!(f, "{}: {}: {}", self.file, self.field, self.msg),
+        }
+    }
+}
+
+/// Collects the problems with the settings of the global config or of a podcast.
+pub struct Diagnostics {
+    file: &'static str,
+    podcast: Option<String>,
+    found: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+    pub fn global() -> Self {
+        Self {
+            file: "config.toml",
+            podcast: None,
+            found: vec![],
+        }
+    }
+
+    /// Problems with the `podcasts.toml` file as a whole.
+    pub fn podcasts() -> Self {
+        Self {
+            file: "podcasts.toml",
+            podcast: None,
+            found: vec![],
+        }
+    }
+
+    pub fn podcast(name: &str) -> Self {
+        Self {
+            file: "podcasts.toml",
+            podcast: Some(name.to_string()),
+            found: vec![],
+        }
+    }
+
+    pub fn error(&mut self, field: &str, msg: impl Into<String>) {
+        self.found.push(Diagnostic {
+            file: self.file,
+            podcast: self.podcast.clone(),
+            field: field.to_string(),
+            msg: msg.into(),
+        });
+    }
+
+    /// Validates the field if it's set.
+    pub fn check<T>(
+        &mut self,
+        field: &str,
+        val: Option<T>,
+        validate: impl FnOnce(T) -> Result<(), String>,
+    ) {
+        if let Some(Err(e)) = val.map(validate) {
+            self.error(field, e);
+        }
+    }
+
+    pub fn into_inner(self) -> Vec<Diagnostic> {
+        self.found
+    }
+}
+
+pub fn pattern(s: impl AsRef<str>) -> Result<(), String> {
+    let s = s.as_ref();
+    FullPattern::parse(s).map(|_| ())
+}
+
+pub fn date(s: impl AsRef<str>) -> Result<(), String> {
+    let s = s.as_ref();
+    match dateparser::parse(s) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(format!("invalid date \"{}\": {}", s, e)),
+    }
+}
+
+pub fn rate(s: impl AsRef<str>) -> Result<(), String> {
+    let s = s.as_ref();
+    match throttle::parse_rate(s) {
+        Some(_) => Ok(()),
+        None => Err(format!("expected a rate like \"2MiB/s\", got \"{}\"", s)),
+    }
+}
+
+pub fn schedule(s: impl AsRef<str>) -> Result<(), String> {
+    let s = s.as_ref();
+    match throttle::Schedule::from_str(s) {
+        Some(_) => Ok(()),
+        None => Err(format!(
+            "expected a time range like \"23:00-07:00\", got \"{}\"",
+            s
+        )),
+    }
+}
+
+pub fn non_negative(val: &i64) -> Result<(), String> {
+    if *val < 0 {
+        Err(format!("can't be negative, got {}", val))
+    } else {
+        Ok(())
+    }
+}
+
+pub fn hook(path: impl AsRef<Path>) -> Result<(), String> {
+    let path = path.as_ref();
+    if path.is_file() {
+        Ok(())
+    } else {
+        Err(format!("no file at {:?}", path))
+    }
+}
+
+/// Validates the config files without touching the network or saving anything.
+///
+/// Prints every problem that's found, and fails with a config error if there are any.
+pub fn run(config_path: Option<&Path>) -> Result<(), Error> {
+    let mut diagnostics = vec![];
+
+    let global_path = config_path
+        .map(Path::to_path_buf)
+        .unwrap_or_else(GlobalConfig::default_path);
+
+    // A missing default config is created with the default settings.
+    let global_config = match fs::read_to_string(&global_path) {
+        Ok(s) => match toml::from_str::<GlobalConfig>(&s) {
+            Ok(config) => config,
+            Err(e) => {
+                let mut global = Diagnostics::global();
+                global.error("parse error", e.to_string());
+                diagnostics.extend(global.into_inner());
+                GlobalConfig::default()
+            }
+        },
+        Err(_) if config_path.is_none() => GlobalConfig::default(),
+        Err(e) => return Err(Error::io(&global_path)(e)),
+    };
+
+    diagnostics.extend(global_config.check());
+
+    let podcasts_path = PodcastConfigs::path();
+    let podcasts = fs::read_to_string(&podcasts_path)
+        .map_err(|e| e.to_string())
+        .and_then(|s| toml::from_str::<PodcastConfigs>(&s).map_err(|e| e.to_string()));
+
+    let podcast_qty = match podcasts {
+        Ok(podcasts) => {
+            diagnostics.extend(podcasts.check());
+            podcasts.len()
+        }
+        Err(e) => {
+            let mut podcasts = Diagnostics::podcasts();
+            podcasts.error("parse error", e);
+            diagnostics.extend(podcasts.into_inner());
+            0
+        }
+    };
+
+    for diagnostic in &diagnostics {
+        println!("{}", diagnostic);
+    }
+
+    if diagnostics.is_empty() {
+        eprintln!("config is valid, checked {} podcasts", podcast_qty);
+        Ok(())
+    } else {
+        Err(Error::Config(format!(
+            "found {} problems",
+            diagnostics.len()
+        )))
+    }
+}
@@ -1,25 +1,41 @@
+use crate::check;
+use crate::check::Diagnostic;
+use crate::check::Diagnostics;
 use crate::display::DownloadBar;
+use crate::display::ProgressFormat;
+use crate::display::ProgressOutput;
 use crate::episode;
-use crate::patterns::Evaluate;
-use crate::patterns::FullPattern;
+use crate::error::Error;
+use crate::events::EventSink;
+use crate::http::{HttpClient, ReqwestClient};
+use crate::limits::ConnectionLimits;
+use crate::patterns::{EvalError, FullPattern};
+use crate::podcast::EpisodeSelection;
 use crate::podcast::Podcast;
 use crate::podcast::RawPodcast;
+use crate::report::EpisodeListing;
+use crate::report::Record;
+use crate::retry::RetryPolicy;
+use crate::throttle;
+use crate::throttle::RateLimiter;
+use crate::toml_merge;
+use crate::transcript::TranscriptType;
 use crate::utils;
+use crate::utils::FetchMode;
 use crate::utils::Unix;
 use futures::future;
 use indicatif::MultiProgress;
 use regex::Regex;
 use serde::de::IntoDeserializer;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
 use std::collections::HashMap;
 use std::fs;
-use std::io::Write;
+use std::future::Future;
 use std::path::Path;
 use std::path::PathBuf;
-use std::process;
-use std::sync::atomic::AtomicBool;
-use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::sync::Mutex;
 use std::time;
 
 /// Represents a [`PodcastConfig`] value that is either enabled, disabled,
@@ -40,6 +56,14 @@ impl<T: Clone> ConfigOption<T> {
         matches!(self, Self::Enabled(_))
     }
 
+    /// The value set for the podcast itself, if any.
+    pub fn enabled(&self) -> Option<&T> {
+        match self {
+            Self::Enabled(t) => Some(t),
+            _ => None,
+        }
+    }
+
     pub fn into_val(self, global_value: Option<&T>) -> Option<T> {
         match self {
             Self::Disabled => None,
@@ -104,6 +128,8 @@ pub struct EvalData<'a> {
     pub pod_name: &'a str,
     pub podcast: &'a RawPodcast,
     pub episode: &'a episode::Attributes,
+    /// The position of the episode from oldest to newest, starting at 0.
+    pub index: usize,
 }
 
 impl<'a> EvalData<'a> {
@@ -111,11 +137,13 @@ impl<'a> EvalData<'a> {
         pod_name: &'a str,
         podcast: &'a RawPodcast,
         episode: &'a episode::Attributes,
+        index: usize,
     ) -> Self {
         Self {
             pod_name,
             podcast,
             episode,
+            index,
         }
     }
 }
@@ -126,7 +154,6 @@ impl<'a> EvalData<'a> {
 /// Must be computed for every episode because config might contain patterns unique to episode.
 #[derive(Debug, Clone, Default)]
 pub struct Config {
-    pub url: String,
     pub name_pattern: String,
     pub id_pattern: String,
     pub download_path: PathBuf,
@@ -135,6 +162,8 @@ pub struct Config {
     pub symlink: Option<PathBuf>,
     pub id3_tags: HashMap<String, String>,
     pub download_hook: Option<PathBuf>,
+    pub transcripts: Option<TranscriptSettings>,
+    pub retry: RetryPolicy,
 }
 
 impl Config {
@@ -142,7 +171,7 @@ impl Config {
         global_config: &GlobalConfig,
         podcast_config: &PodcastConfig,
         data: EvalData<'_>,
-    ) -> Self {
+    ) -> Result<Self, EvalError> {
         let podcast_config = podcast_config.to_owned();
         let id3_tags = {
             let mut map = HashMap::with_capacity(
@@ -159,15 +188,23 @@ impl Config {
             map
         };
 
+        let retry = podcast_config.retry_policy(global_config);
+        let strict = podcast_config.strict_patterns(global_config);
+
         let download_hook = podcast_config
             .download_hook
             .into_val(global_config.download_hook.as_ref());
 
+        let transcripts = podcast_config
+            .transcripts
+            .into_val(global_config.transcripts.as_ref());
+
         let download_path_str = podcast_config
             .download_path
             .unwrap_or_else(|| global_config.download_path.clone());
 
-        let download_path = FullPattern::direct_eval_dir(&download_path_str, data);
+        // Directories are only created once something is downloaded to them.
+        let download_path = FullPattern::direct_eval_path(&download_path_str, data, strict)?;
 
         let tracker_path = match podcast_config
             .tracker_path
@@ -183,33 +220,35 @@ impl Config {
             }
         };
 
-        let tracker_path = FullPattern::direct_eval_file(&tracker_path, data);
+        let tracker_path = FullPattern::direct_eval_path(&tracker_path, data, strict)?;
 
-        let name_pattern = FullPattern::from_str(
+        let name_pattern = FullPattern::direct_eval(
             &podcast_config
                 .name_pattern
                 .unwrap_or_else(|| global_config.name_pattern.clone()),
-        )
-        .evaluate(data);
+            data,
+            strict,
+        )?;
 
         let id_pattern = podcast_config
             .id_pattern
             .unwrap_or_else(|| global_config.id_pattern.clone());
 
-        let id_pattern = FullPattern::from_str(&id_pattern).evaluate(data);
+        let id_pattern = FullPattern::direct_eval(&id_pattern, data, strict)?;
 
         let symlink = podcast_config
             .symlink
             .or(global_config.symlink.clone())
-            .map(|str| FullPattern::direct_eval_dir(str.as_ref(), data));
+            .map(|str| FullPattern::direct_eval_path(str.as_ref(), data, strict))
+            .transpose()?;
 
         let partial_path = podcast_config
             .partial_path
             .or(global_config.partial_path.clone())
-            .map(|str| FullPattern::direct_eval_dir(str.as_ref(), data));
+            .map(|str| FullPattern::direct_eval_path(str.as_ref(), data, strict))
+            .transpose()?;
 
-        Config {
-            url: podcast_config.url.clone(),
+        Ok(Config {
             name_pattern,
             id_pattern,
             download_path,
@@ -218,7 +257,29 @@ impl Config {
             symlink,
             id3_tags: id3_tags.clone(),
             download_hook: download_hook.clone(),
-        }
+            transcripts,
+            retry,
+        })
+    }
+}
+
+/// Settings for downloading the `<podcast:transcript>` of episodes.
+#[derive(Serialize, Default, Deserialize, Debug, PartialEq, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct TranscriptSettings {
+    /// Preferred transcript formats, in order of preference.
+    types: Option<Vec<TranscriptType>>,
+    /// Whether to embed the transcript in the ID3v2 tags of MP3 files.
+    embed: Option<bool>,
+}
+
+impl TranscriptSettings {
+    pub fn types(&self) -> &[TranscriptType] {
+        self.types.as_deref().unwrap_or_default()
+    }
+
+    pub fn embed(&self) -> bool {
+        self.embed.unwrap_or(false)
     }
 }
 
@@ -276,6 +337,10 @@ impl IndicatifSettings {
         "❌ {msg}".to_owned()
     }
 
+    fn default_partial_template() -> String {
+        "⚠️  {msg}".to_owned()
+    }
+
     fn default_hooks() -> String {
         "{spinner:.green} finishing up download hooks...".to_string()
     }
@@ -288,6 +353,10 @@ impl IndicatifSettings {
         Self::default_podcast_fetch_template()
     }
 
+    pub fn podcast_waiting_template() -> String {
+        "{spinner:.green}  {msg}waiting...".to_string()
+    }
+
     pub fn download_template(&self) -> String {
         self.download_bar
             .clone()
@@ -304,6 +373,10 @@ impl IndicatifSettings {
         Self::default_error_template()
     }
 
+    pub fn partial_template(&self) -> String {
+        Self::default_partial_template()
+    }
+
     pub fn hook_template(&self) -> String {
         self.hooks
             .clone()
@@ -347,73 +420,58 @@ pub struct GlobalConfig {
     #[serde(default, skip_serializing_if = "SearchSettings::is_default")]
     search: SearchSettings,
     symlink: Option<String>,
+    update_feed_url: Option<bool>,
+    strict_patterns: Option<bool>,
+    retries: Option<u32>,
+    retry_backoff_ms: Option<u64>,
+    retry_max_delay_ms: Option<u64>,
+    max_concurrent_podcasts: Option<usize>,
+    max_connections_per_host: Option<usize>,
+    parallel_episodes: Option<usize>,
+    max_download_rate: Option<String>,
+    unthrottled_hours: Option<String>,
+    progress: Option<ProgressFormat>,
+    progress_socket: Option<PathBuf>,
     #[serde(default, skip_serializing_if = "LogConfig::is_default")]
     log: Arc<LogConfig>,
+    transcripts: Option<TranscriptSettings>,
 }
 
 impl GlobalConfig {
     /// Loads the global config from the default path.
     ///
     /// If config is not present it'll create a default one.
-    /// It'll always save after loading in case it's missing a required field
-    /// which has a default. Reason is I want the user to see all the required
-    /// fields in the `config.toml` file instead of just silently using the default
-    /// value. This also makes the user aware of any new required fields after updating.
-    /// Note that this means any comments will unfortunately be removed.
-    pub fn load() -> Self {
+    /// An existing config is never written to, so its comments and formatting are kept.
+    pub fn load() -> Result<Self, Error> {
         let path = Self::default_path();
         if !path.exists() {
             let config = Self::default();
-            config.save();
-            return config;
+            config.save()?;
+            return Ok(config);
         }
 
-        let str = match fs::read_to_string(&path) {
-            Ok(str) => str,
-            Err(e) => {
-                eprintln!("unable to read config file: {:?}", e);
-                process::exit(1);
-            }
-        };
-
-        let config: Self = match toml::from_str(&str) {
-            Ok(config) => config,
-            Err(e) => {
-                eprintln!("unable to parse config file: {:?}", e);
-                process::exit(1);
-            }
-        };
-
-        config.save();
-        config
+        let str = fs::read_to_string(&path).map_err(Error::io(&path))?;
+        toml::from_str(&str)
+            .map_err(|e| Error::Config(format!("unable to parse config file: {}", e)))
     }
 
     /// For using a global config from a path specified as a commandline argument.
     /// Main difference from the normal loading is that it won't create a default one if it's missing.
-    pub fn load_from_path(path: &Path) -> Self {
+    pub fn load_from_path(path: &Path) -> Result<Self, Error> {
         if !path.exists() {
-            eprintln!("no config located at {:?}", path);
-            process::exit(1);
+            return Err(Error::Config(format!("no config located at {:?}", path)));
         };
 
-        let str = match fs::read_to_string(&path) {
-            Ok(str) => str,
-            Err(e) => {
-                eprintln!("unable to read given config file:{:?}\n{:?}", path, e);
-                process::exit(1);
-            }
-        };
-
-        match toml::from_str(&str) {
-            Ok(config) => config,
-            Err(e) => {
-                eprintln!("unable to parse given config file: {:?}\n{:?}", path, e);
-                process::exit(1);
-            }
-        }
+        let str = fs::read_to_string(path).map_err(Error::io(path))?;
+        toml::from_str(&str).map_err(|e| {
+            Error::Config(format!(
+                "unable to parse given config file: {:?}\n{}",
+                path, e
+            ))
+        })
     }
 
-    pub fn style(&self) -> Arc<IndicatifSettings> {
+    pub(crate) fn style(&self) -> Arc<IndicatifSettings> {
         Arc::clone(&self.style)
     }
 
@@ -421,19 +479,23 @@ impl GlobalConfig {
         Arc::clone(&self.log)
     }
 
-    /// Serializes the config to the default path.
-    pub fn save(&self) {
+    /// Serializes the config to the default path, keeping the formatting of the existing file.
+    pub fn save(&self) -> Result<(), Error> {
         let path = Self::default_path();
-        let str = toml::to_string(self).unwrap();
-        let mut f = std::fs::File::create(&path).expect("unable to create config file");
-        f.write_all(str.as_bytes()).unwrap();
+        let original = fs::read_to_string(&path).unwrap_or_default();
+        let str = toml_merge::merge(&original, self).map_err(Error::Config)?;
+        if str == original {
+            return Ok(());
+        }
+
+        fs::write(&path, str).map_err(Error::io(&path))
     }
 
     pub fn user_agent(&self) -> String {
         self.user_agent.clone().unwrap_or_else(default_user_agent)
     }
 
-    pub fn search_settings(&self) -> &SearchSettings {
+    pub(crate) fn search_settings(&self) -> &SearchSettings {
         &self.search
     }
 
@@ -448,6 +510,119 @@ impl GlobalConfig {
     pub fn max_line_width(&self) -> usize {
         self.search.line_width.unwrap_or(79)
     }
+
+    /// Finds the invalid settings, see [`check::run`].
+    pub(crate) fn check(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = Diagnostics::global();
+
+        diagnostics.check("download_path", Some(&self.download_path), check::pattern);
+        diagnostics.check("partial_path", self.partial_path.as_ref(), check::pattern);
+        diagnostics.check("name_pattern", Some(&self.name_pattern), check::pattern);
+        diagnostics.check("id_pattern", Some(&self.id_pattern), check::pattern);
+        diagnostics.check("tracker_path", self.tracker_path.as_ref(), check::pattern);
+        diagnostics.check("symlink", self.symlink.as_ref(), check::pattern);
+        diagnostics.check("max_days", self.max_days.as_ref(), check::non_negative);
+        diagnostics.check(
+            "max_episodes",
+            self.max_episodes.as_ref(),
+            check::non_negative,
+        );
+        diagnostics.check("earliest_date", self.earliest_date.as_ref(), check::date);
+        diagnostics.check("download_hook", self.download_hook.as_deref(), check::hook);
+        diagnostics.check(
+            "max_download_rate",
+            self.max_download_rate.as_ref(),
+            check::rate,
+        );
+        diagnostics.check(
+            "unthrottled_hours",
+            self.unthrottled_hours.as_ref(),
+            check::schedule,
+        );
+
+        diagnostics.into_inner()
+    }
+
+    /// Overrides the progress format of the config, like with `--progress`.
+    pub fn set_progress(&mut self, format: ProgressFormat) {
+        self.progress = Some(format);
+    }
+
+    /// Prints a status message to stderr, unless the JSON progress events are written there.
+    pub(crate) fn notice(&self, msg: impl std::fmt::Display) {
+        log::info!("{}", msg);
+        if !self.has_quiet_stderr() {
+            eprintln!("{}", msg);
+        }
+    }
+
+    /// Whether stderr is reserved for the JSON progress events.
+    pub fn has_quiet_stderr(&self) -> bool {
+        self.progress == Some(ProgressFormat::Json) && self.progress_socket.is_none()
+    }
+
+    /// Where the progress of a sync is shown. JSON events are written to the
+    /// `progress_socket` if there is one, or to stderr otherwise.
+    pub(crate) fn progress_output(
+        &self,
+        longest_podcast_name: usize,
+    ) -> Result<ProgressOutput, Error> {
+        let output = match self.progress.unwrap_or_default() {
+            ProgressFormat::Bars => ProgressOutput::Bars {
+                mp: MultiProgress::new(),
+                settings: self.style(),
+                longest_podcast_name,
+            },
+            ProgressFormat::Json => match &self.progress_socket {
+                Some(path) => {
+                    ProgressOutput::Json(EventSink::connect(path).map_err(Error::io(path))?)
+                }
+                None => ProgressOutput::Json(EventSink::stderr()),
+            },
+        };
+
+        Ok(output)
+    }
+
+    pub(crate) fn connection_limits(&self) -> Result<ConnectionLimits, Error> {
+        let bandwidth = self.rate_limiter(self.max_download_rate.as_deref())?;
+
+        Ok(ConnectionLimits::new(
+            self.max_concurrent_podcasts,
+            self.max_connections_per_host,
+            bandwidth,
+        ))
+    }
+
+    /// A rate limiter for the given `max_download_rate`, which runs at full speed during
+    /// the `unthrottled_hours`.
+    fn rate_limiter(&self, rate: Option<&str>) -> Result<Option<RateLimiter>, Error> {
+        let Some(rate) = rate else {
+            return Ok(None);
+        };
+
+        let Some(rate) = throttle::parse_rate(rate) else {
+            return Err(Error::Config(format!(
+                "invalid max_download_rate: \"{}\"\nexpected a rate like \"2MiB/s\" or \"500KB/s\"",
+                rate
+            )));
+        };
+
+        let unthrottled = match &self.unthrottled_hours {
+            Some(hours) => match throttle::Schedule::from_str(hours) {
+                Some(schedule) => Some(schedule),
+                None => {
+                    return Err(Error::Config(format!(
+                    "invalid unthrottled_hours: \"{}\"\nexpected a time range like \"23:00-07:00\"",
+                    hours
+                )))
+                }
+            },
+            None => None,
+        };
+
+        Ok(Some(RateLimiter::new(rate, unthrottled)))
+    }
 }
 
 impl Default for GlobalConfig {
@@ -468,6 +643,19 @@ impl Default for GlobalConfig {
             symlink: None,
             user_agent: None,
             partial_path: None,
+            transcripts: None,
+            update_feed_url: None,
+            strict_patterns: None,
+            retries: None,
+            retry_backoff_ms: None,
+            retry_max_delay_ms: None,
+            max_concurrent_podcasts: None,
+            max_connections_per_host: None,
+            parallel_episodes: None,
+            max_download_rate: None,
+            unthrottled_hours: None,
+            progress: None,
+            progress_socket: None,
         }
     }
 }
@@ -486,8 +674,11 @@ pub enum DownloadMode {
 }
 
 impl DownloadMode {
-    pub fn new(global_config: &GlobalConfig, podcast_config: &PodcastConfig) -> Self {
-        match (
+    pub fn new(
+        global_config: &GlobalConfig,
+        podcast_config: &PodcastConfig,
+    ) -> Result<Self, Error> {
+        let mode = match (
             podcast_config.backlog_start.clone(),
             podcast_config.backlog_interval.clone(),
         ) {
@@ -499,52 +690,79 @@ impl DownloadMode {
                 max_episodes: podcast_config
                     .max_episodes
                     .into_val(global_config.max_episodes.as_ref()),
-                earliest_date: {
-                    podcast_config
-                        .earliest_date
-                        .clone()
-                        .into_val(global_config.earliest_date.as_ref())
-                        .map(|date| {
-                            utils::date_str_to_unix(&date)
-                                .expect("failed to parse earliest_date string")
-                        })
-                },
+                earliest_date: podcast_config
+                    .earliest_date
+                    .clone()
+                    .into_val(global_config.earliest_date.as_ref())
+                    .map(|date| utils::date_str_to_unix(&date))
+                    .transpose()
+                    .map_err(Error::Config)?,
             },
-            (Some(_), None) => {
-                eprintln!("missing backlog_interval");
-                std::process::exit(1);
-            }
-            (None, Some(_)) => {
-                eprintln!("missing backlog_start");
-                std::process::exit(1);
-            }
+            (Some(_), None) => return Err(Error::Config("missing backlog_interval".into())),
+            (None, Some(_)) => return Err(Error::Config("missing backlog_start".into())),
             (Some(start), Some(interval)) => {
                 if podcast_config.max_days.is_enabled() {
-                    eprintln!("'max_days' not compatible with backlog mode.");
-                    std::process::exit(1);
+                    return Err(Error::Config(
+                        "'max_days' not compatible with backlog mode.".into(),
+                    ));
                 }
 
                 if podcast_config.max_episodes.is_enabled() {
-                    eprintln!("'max_episodes' not compatible with backlog mode.");
-                    eprintln!("If you want to limit the amount of episodes to download, consider changing the 'backlog_start' setting.");
-                    std::process::exit(1);
+                    return Err(Error::Config("'max_episodes' not compatible with backlog mode.\nIf you want to limit the amount of episodes to download, consider changing the 'backlog_start' setting.".into()));
                 }
 
                 if podcast_config.earliest_date.is_enabled() {
-                    eprintln!("'earliest_date' not compatible with backlog mode.");
-                    std::process::exit(1);
+                    return Err(Error::Config(
+                        "'earliest_date' not compatible with backlog mode.".into(),
+                    ));
                 }
 
                 let Ok(start) = dateparser::parse(&start) else {
-                    eprintln!("invalid backlog_start format.");
-                    std::process::exit(1);
+                    return Err(Error::Config("invalid backlog_start format.".into()));
                 };
 
+                if interval < 1 {
+                    return Err(Error::Config(
+                        "'backlog_interval' must be at least 1 day.".into(),
+                    ));
+                }
+
                 DownloadMode::Backlog {
                     start: std::time::Duration::from_secs(start.timestamp() as u64),
                     interval: Unix::from_secs(interval as u64 * 86400),
                 }
             }
+        };
+
+        Ok(mode)
+    }
+
+    /// Whether the episode at `index`, from oldest to newest, is one this mode downloads.
+    pub fn includes(&self, published: Unix, index: usize, episode_qty: usize) -> bool {
+        match self {
+            DownloadMode::Backlog { start, interval } => {
+                let time_passed = utils::current_unix() - *start;
+                let intervals_passed = time_passed.as_secs() / interval.as_secs();
+                intervals_passed >= index as u64
+            }
+
+            DownloadMode::Standard {
+                max_time,
+                max_episodes,
+                earliest_date,
+            } => {
+                let max_time_exceeded = max_time.map_or(false, |max_time| {
+                    (utils::current_unix() - published) > max_time
+                });
+
+                let max_episodes_exceeded = max_episodes.map_or(false, |max_episodes| {
+                    episode_qty.saturating_sub(max_episodes as usize) > index
+                });
+
+                let episode_too_old = earliest_date.map_or(false, |date| date > published);
+
+                !max_time_exceeded && !max_episodes_exceeded && !episode_too_old
+            }
         }
     }
 }
@@ -559,87 +777,277 @@ impl Default for DownloadMode {
     }
 }
 
-fn init_reqwest_client(config: &GlobalConfig) -> Arc<reqwest::Client> {
-    reqwest::Client::builder()
-        .user_agent(&config.user_agent())
-        .build()
-        .map(Arc::new)
-        .expect("error: failed to instantiate reqwest client")
-}
-
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(transparent)]
 pub struct PodcastConfigs(HashMap<String, PodcastConfig>);
 
 impl PodcastConfigs {
-    pub async fn sync(self, global_config: GlobalConfig, log_file: &Path) -> Vec<PathBuf> {
-        eprintln!("syncing {} podcasts", self.len());
-        log::info!("syncing podcasts..");
+    pub async fn sync(
+        self,
+        global_config: GlobalConfig,
+        log_file: &Path,
+        fetch_mode: FetchMode,
+    ) -> Result<Vec<Record>, Error> {
+        global_config.notice(format!("syncing {} podcasts", self.len()));
 
-        let mp = MultiProgress::new();
         let global_config = Arc::new(global_config);
-        let client = init_reqwest_client(&global_config);
+
+        let (results, moved_feeds) = self
+            .for_each_podcast(&global_config, fetch_mode, |podcast, mut ui| async move {
+                podcast.sync(&mut ui).await
+            })
+            .await?;
+
+        let error_occured = results.iter().any(|(_, result)| result.is_err());
+        let records = Self::into_records(results);
+
+        Self::handle_moved_feeds(moved_feeds, &global_config)?;
+
+        if let Some(p) = global_config.log().path() {
+            if true || error_occured {
+                // The episodes are downloaded already, so this shouldn't fail the sync.
+                if let Err(e) = Self::move_log(log_file, p) {
+                    eprintln!("failed to move log file: {}", e);
+                }
+            }
+        }
+
+        Ok(records)
+    }
+
+    fn move_log(log_file: &Path, dir: &Path) -> Result<(), Error> {
+        utils::create_dir(dir)?;
+        let log_name = log_file.file_name().unwrap_or_default();
+        fs::rename(log_file, dir.join(log_name)).map_err(Error::io(log_file))
+    }
+
+    /// Downloads the selected episodes of the podcasts, ignoring their download mode.
+    pub async fn download(
+        self,
+        global_config: GlobalConfig,
+        selection: EpisodeSelection,
+        fetch_mode: FetchMode,
+    ) -> Result<Vec<Record>, Error> {
+        global_config.notice(format!(
+            "downloading selected episodes of {} podcasts",
+            self.len()
+        ));
+
+        let global_config = Arc::new(global_config);
+        let selection = Arc::new(selection);
+
+        let (results, moved_feeds) = self
+            .for_each_podcast(&global_config, fetch_mode, move |podcast, mut ui| {
+                let selection = Arc::clone(&selection);
+                async move { podcast.download_selected(&selection, &mut ui).await }
+            })
+            .await?;
+
+        Self::handle_moved_feeds(moved_feeds, &global_config)?;
+        Ok(Self::into_records(results))
+    }
+
+    /// Flattens the records of every podcast, with an error record for podcasts that failed.
+    fn into_records(results: Vec<(String, Result<Vec<Record>, Error>)>) -> Vec<Record> {
+        let mut records = vec![];
+        for (podcast, result) in results {
+            match result {
+                Ok(podcast_records) => records.extend(podcast_records),
+                Err(error) => records.push(Record::Error { podcast, error }),
+            }
+        }
+        records
+    }
+
+    /// Fetches the podcasts and reports what a sync would do, without downloading
+    /// anything or touching the download directories.
+    pub async fn dry_run(
+        self,
+        global_config: GlobalConfig,
+        fetch_mode: FetchMode,
+    ) -> Result<Vec<String>, Error> {
+        global_config.notice(format!("checking {} podcasts", self.len()));
+        log::info!("dry-running sync..");
+
+        let global_config = Arc::new(global_config);
+
+        let (results, moved_feeds) = self
+            .for_each_podcast(&global_config, fetch_mode, |podcast, mut ui| async move {
+                let plan = podcast.plan();
+                ui.complete();
+                plan
+            })
+            .await?;
+
+        for (name, config) in moved_feeds {
+            global_config.notice(format!(
+                "'{}' has moved, url would be updated to: {}",
+                &name, &config.url
+            ));
+        }
+
+        let mut plans: Vec<String> = results
+            .into_iter()
+            .map(|(name, result)| {
+                result.unwrap_or_else(|error| format!("{}\n  failed: {}", name, error))
+            })
+            .collect();
+        plans.sort();
+        Ok(plans)
+    }
+
+    /// Fetches the podcasts and lists all their episodes, sorted by podcast and publish date.
+    pub async fn episodes(
+        self,
+        global_config: GlobalConfig,
+        fetch_mode: FetchMode,
+    ) -> Result<Vec<EpisodeListing>, Error> {
+        let global_config = Arc::new(global_config);
+
+        let (results, _) = self
+            .for_each_podcast(&global_config, fetch_mode, |podcast, mut ui| async move {
+                let listings = podcast.listings();
+                ui.complete();
+                listings
+            })
+            .await?;
+
+        let mut results: Vec<(String, Vec<EpisodeListing>)> = results
+            .into_iter()
+            .filter_map(|(name, result)| Some((name, result.ok()?)))
+            .collect();
+        results.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(results
+            .into_iter()
+            .flat_map(|(_, listings)| listings)
+            .collect())
+    }
+
+    /// Fetches every podcast concurrently, each with its own bar, and runs the action on them.
+    ///
+    /// Returns the name of every podcast with the output of the action, or the error if
+    /// the podcast couldn't be fetched, along with the podcasts whose feed has moved.
+    /// Only fails if the podcasts can't be fetched at all, like with invalid global settings.
+    async fn for_each_podcast<T, F, Fut>(
+        self,
+        global_config: &Arc<GlobalConfig>,
+        fetch_mode: FetchMode,
+        action: F,
+    ) -> Result<
+        (
+            Vec<(String, Result<T, Error>)>,
+            Vec<(String, PodcastConfig)>,
+        ),
+        Error,
+    >
+    where
+        F: Fn(Podcast, DownloadBar) -> Fut + Clone + Send + 'static,
+        Fut: Future<Output = T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let client: Arc<dyn HttpClient> = Arc::new(ReqwestClient::new(global_config)?);
+        let limits = Arc::new(global_config.connection_limits()?);
 
         let Some(longest_name) = self.longest_name() else {
-            return vec![];
+            return Ok((vec![], vec![]));
         };
+        let progress = global_config.progress_output(longest_name)?;
 
-        let error_occured = Arc::new(AtomicBool::new(false));
+        let moved_feeds: Arc<Mutex<Vec<(String, PodcastConfig)>>> = Default::default();
 
         let futures = self
             .into_inner()
             .into_iter()
             .map(|(name, config)| {
                 let client = Arc::clone(&client);
-                let settings = global_config.style();
-                let mut ui = DownloadBar::new(name.clone(), settings, &mp, longest_name);
-                let global_config = Arc::clone(&global_config);
-                let val = error_occured.clone();
+                let limits = Arc::clone(&limits);
+                let moved_feeds = Arc::clone(&moved_feeds);
+                let mut ui = DownloadBar::new(name.clone(), &progress);
+                let global_config = Arc::clone(global_config);
+                let action = action.clone();
 
                 tokio::task::spawn(async move {
-                    match Podcast::new(name, config, &global_config, client, &ui).await {
-                        Ok(podcast) => podcast.sync(&mut ui).await,
+                    ui.waiting();
+                    let _permit = limits.podcast_permit().await;
+
+                    let podcast = Podcast::new(
+                        name.clone(),
+                        config.clone(),
+                        &global_config,
+                        client,
+                        limits,
+                        fetch_mode,
+                        &ui,
+                    )
+                    .await;
+
+                    match podcast {
+                        Ok(podcast) => {
+                            if let Some(new_url) = podcast.new_url() {
+                                ui.log_warn(format!("feed has moved to: {}", new_url));
+                                let mut config = config;
+                                config.url = new_url.to_string();
+                                moved_feeds.lock().unwrap().push((name.clone(), config));
+                            }
+
+                            (name, Ok(action(podcast, ui).await))
+                        }
                         Err(e) => {
-                            ui.error(&e);
-                            val.store(true, Ordering::SeqCst);
-                            vec![]
+                            ui.error(&e.to_string());
+                            (name, Err(e))
                         }
                     }
                 })
             })
             .collect::<Vec<_>>();
 
-        let paths: Vec<PathBuf> = future::join_all(futures)
+        let results = future::join_all(futures)
             .await
             .into_iter()
             .filter_map(Result::ok)
-            .flatten()
             .collect();
 
-        if let Some(p) = global_config.log().path() {
-            if true || error_occured.load(Ordering::SeqCst) {
-                utils::create_dir(p);
-                let log_name = log_file.file_name().unwrap();
-                let new_path = p.join(log_name);
-                fs::rename(log_file, new_path).unwrap();
+        let moved_feeds = std::mem::take(&mut *moved_feeds.lock().unwrap());
+        Ok((results, moved_feeds))
+    }
+
+    /// Updates the url of podcasts whose feed has moved, unless disabled in the config.
+    fn handle_moved_feeds(
+        moved_feeds: Vec<(String, PodcastConfig)>,
+        global_config: &GlobalConfig,
+    ) -> Result<(), Error> {
+        let mut updated = HashMap::new();
+
+        for (name, config) in moved_feeds {
+            if config.update_feed_url(global_config) {
+                global_config.notice(format!(
+                    "'{}' has moved, updating url to: {}",
+                    &name, &config.url
+                ));
+                updated.insert(name, config);
+            } else {
+                global_config.notice(format!(
+                    "warning: '{}' has moved to: {}\nUpdate the url in {:?} to keep syncing it.",
+                    &name,
+                    &config.url,
+                    Self::path()
+                ));
             }
         }
 
-        paths
+        if !updated.is_empty() {
+            Self(updated).save_modified()?;
+        }
+
+        Ok(())
     }
 
-    pub fn load() -> Self {
-        let Ok(config_str) = fs::read_to_string(&Self::path()) else {
-            eprintln!("error: failed to read podcasts.toml file");
-            process::exit(1);
-        };
+    pub fn load() -> Result<Self, Error> {
+        let path = Self::path();
+        let config_str = fs::read_to_string(&path).map_err(Error::io(&path))?;
 
-        match toml::from_str(&config_str) {
-            Ok(s) => Self(s),
-            Err(e) => {
-                eprintln!("failed to deserialize podcasts.toml file\n{:?}", e);
-                process::exit(1);
-            }
-        }
+        toml::from_str(&config_str)
+            .map_err(|e| Error::Config(format!("failed to deserialize podcasts.toml file\n{}", e)))
     }
 
     fn into_inner(self) -> HashMap<String, PodcastConfig> {
@@ -655,23 +1063,26 @@ impl PodcastConfigs {
         self
     }
 
-    pub fn assert_not_empty(self) -> Self {
+    pub fn assert_not_empty(self) -> Result<Self, Error> {
         if self.is_empty() {
-            eprintln!("No podcasts configured!");
-            eprintln!("You can add podcasts with the following methods:\n");
-            eprintln!("* \"{} --search <name of podcast>\"", crate::APPNAME);
-            eprintln!(
-                "* \"{} --add <feed url>  <name of podcast>\"",
+            let mut msg = "No podcasts configured!\n".to_string();
+            msg.push_str("You can add podcasts with the following methods:\n\n");
+            msg.push_str(&format!(
+                "* \"{} --search <name of podcast>\"\n",
                 crate::APPNAME
-            );
-            eprintln!(
+            ));
+            msg.push_str(&format!(
+                "* \"{} --add <feed url>  <name of podcast>\"\n",
+                crate::APPNAME
+            ));
+            msg.push_str(&format!(
                 "*  Manually configuring the {:?} file.",
                 &PodcastConfigs::path()
-            );
-            process::exit(1);
+            ));
+            return Err(Error::Config(msg));
         }
 
-        self
+        Ok(self)
     }
 
     pub fn longest_name(&self) -> Option<usize> {
@@ -680,8 +1091,8 @@ impl PodcastConfigs {
 
     /// All podcasts matching the regex will only download upcoming episodes.
     /// time. Podcasts with backlog mode ignored.
-    pub fn catch_up(filter: Option<Regex>) {
-        let mut podcasts = Self::load().filter(filter);
+    pub fn catch_up(filter: Option<Regex>) -> Result<(), Error> {
+        let mut podcasts = Self::load()?.filter(filter);
 
         for (name, config) in &mut podcasts.0 {
             if config.catch_up() {
@@ -689,76 +1100,265 @@ impl PodcastConfigs {
             }
         }
 
-        podcasts.save_modified();
+        podcasts.save_modified()
     }
 
     pub fn len(&self) -> usize {
         self.0.len()
     }
 
+    /// Finds the invalid settings of every podcast, sorted by podcast.
+    pub(crate) fn check(&self) -> Vec<Diagnostic> {
+        let mut podcasts: Vec<_> = self.0.iter().collect();
+        podcasts.sort_by_key(|(name, _)| *name);
+
+        podcasts
+            .into_iter()
+            .flat_map(|(name, config)| config.check(name))
+            .collect()
+    }
+
     pub fn is_empty(&self) -> bool {
         self.len() == 0
     }
 
-    pub fn save_modified(self) {
-        let mut all_podcasts = Self::load();
+    pub fn save_modified(self) -> Result<(), Error> {
+        let mut all_podcasts = Self::load()?;
         for (name, config) in self.0 {
             all_podcasts.0.insert(name, config);
         }
 
-        all_podcasts.save_to_file();
+        all_podcasts.save_to_file()
     }
 
-    pub fn save_to_file(self) {
-        use std::fs::File;
-
-        let str = toml::to_string(&self).expect("failed to serialize podcastconfigs");
+    /// Saves the podcasts, only touching the keys that changed in the existing file.
+    pub fn save_to_file(self) -> Result<(), Error> {
         let path = Self::path();
+        let original = fs::read_to_string(&path).unwrap_or_default();
+        let str = toml_merge::merge(&original, &self).map_err(Error::Config)?;
+        if str == original {
+            return Ok(());
+        }
 
-        if let Err(e) = File::create(&path).map(|mut file| file.write_all(str.as_bytes())) {
-            eprintln!("failed to save podcast configs to file: {:?}", e);
-            process::exit(1);
-        };
+        fs::write(&path, str).map_err(Error::io(&path))
     }
 
-    pub fn extend(new_podcasts: HashMap<String, PodcastConfig>) {
-        let mut podcasts = Self::load();
+    pub fn extend(new_podcasts: HashMap<String, PodcastConfig>) -> Result<(), Error> {
+        let mut podcasts = Self::load()?;
         for (name, podcast) in new_podcasts {
             if !podcasts.0.contains_key(&name) {
                 podcasts.0.insert(name, podcast);
             }
         }
 
-        podcasts.save_to_file();
+        podcasts.save_to_file()
+    }
+
+    /// Removes the podcast from the `podcasts.toml` file.
+    ///
+    /// With `delete_files`, its download directories, trackers and symlink directories are
+    /// deleted as well after asking for confirmation, except for the ones that don't depend
+    /// on the name of the podcast, as other podcasts might share them. If neither the feed
+    /// nor a cached copy of it can be loaded, the podcast is removed without its files.
+    pub async fn remove(
+        name: &str,
+        delete_files: bool,
+        global_config: GlobalConfig,
+        fetch_mode: FetchMode,
+    ) -> Result<(), Error> {
+        let mut podcasts = Self::load()?;
+        let Some(config) = podcasts.0.remove(name) else {
+            return Err(Error::Usage(format!("'{}' doesn't exist!", name)));
+        };
+
+        if delete_files {
+            let other_name = format!("_{}", name);
+            match Self::renamed_paths(name, config, &other_name, global_config, fetch_mode).await {
+                Ok(paths) => delete_podcast_files(name, &paths)?,
+                Err(e) => {
+                    eprintln!("can't find the files of '{}': {}", name, e);
+                    eprintln!("only removing it from the podcasts.toml file");
+                }
+            }
+        }
+
+        podcasts.save_to_file()?;
+        eprintln!("'{}' removed!", name);
+        Ok(())
+    }
+
+    /// Renames the podcast in the `podcasts.toml` file.
+    ///
+    /// With `move_files`, the download directories, trackers and symlink directories that
+    /// depend on the name of the podcast are moved to where they'd be under the new name, so
+    /// the episodes aren't downloaded again. Symlinks to the moved files are updated.
+    ///
+    /// Nothing is renamed if any of the files can't be moved.
+    pub async fn rename(
+        old_name: &str,
+        new_name: &str,
+        move_files: bool,
+        global_config: GlobalConfig,
+        fetch_mode: FetchMode,
+    ) -> Result<(), Error> {
+        let podcasts = Self::load()?;
+        if podcasts.0.contains_key(new_name) {
+            return Err(Error::Usage(format!("'{}' already exists!", new_name)));
+        }
+
+        let Some(config) = podcasts.0.get(old_name) else {
+            return Err(Error::Usage(format!("'{}' doesn't exist!", old_name)));
+        };
+
+        if move_files {
+            let paths = Self::renamed_paths(
+                old_name,
+                config.clone(),
+                new_name,
+                global_config,
+                fetch_mode,
+            )
+            .await?;
+
+            // Trackers inside of a download directory are moved along with it.
+            let moves: Vec<&(PathBuf, PathBuf)> = paths
+                .all()
+                .filter(|(from, to)| from != to && from.exists())
+                .collect();
+
+            for (from, to) in &moves {
+                if to.exists() {
+                    return Err(Error::Usage(format!(
+                        "can't move {:?} as {:?} already exists",
+                        from, to
+                    )));
+                }
+            }
+
+            move_all(&moves)?;
+
+            for (_, symlink_dir) in &paths.symlink_dirs {
+                if let Err(e) = relink(symlink_dir, &moves) {
+                    eprintln!("failed to update the symlinks in {:?}: {}", symlink_dir, e);
+                }
+            }
+        }
+
+        let path = Self::path();
+        let original = fs::read_to_string(&path).map_err(Error::io(&path))?;
+        let str = toml_merge::rename_table(&original, old_name, new_name).map_err(Error::Config)?;
+        fs::write(&path, str).map_err(Error::io(&path))?;
+
+        eprintln!("'{}' renamed to '{}'!", old_name, new_name);
+        Ok(())
+    }
+
+    /// Fetches the podcast and pairs up the paths of its episodes with where they'd be if
+    /// the podcast was named `new_name` instead.
+    ///
+    /// Falls back to the cached feed if the feed can't be fetched, as it might be gone.
+    async fn renamed_paths(
+        name: &str,
+        config: PodcastConfig,
+        new_name: &str,
+        global_config: GlobalConfig,
+        fetch_mode: FetchMode,
+    ) -> Result<RenamedPaths, Error> {
+        let global_config = Arc::new(global_config);
+        let result =
+            Self::fetch_renamed_paths(name, config.clone(), new_name, &global_config, fetch_mode)
+                .await;
+
+        match result {
+            Err(Error::Network(e)) if fetch_mode != FetchMode::Offline => {
+                eprintln!("{}, using the cached feed of '{}'", e, name);
+                let offline = FetchMode::Offline;
+                Self::fetch_renamed_paths(name, config, new_name, &global_config, offline)
+                    .await
+                    .map_err(|_| Error::Network(e))
+            }
+            result => result,
+        }
+    }
+
+    async fn fetch_renamed_paths(
+        name: &str,
+        config: PodcastConfig,
+        new_name: &str,
+        global_config: &Arc<GlobalConfig>,
+        fetch_mode: FetchMode,
+    ) -> Result<RenamedPaths, Error> {
+        let podcasts = Self(HashMap::from([(name.to_string(), config)]));
+
+        let action = {
+            let global_config = Arc::clone(global_config);
+            let name = name.to_string();
+            let new_name = new_name.to_string();
+
+            move |podcast: Podcast, mut ui: DownloadBar| {
+                let global_config = Arc::clone(&global_config);
+                let name = name.clone();
+                let new_name = new_name.clone();
+
+                async move {
+                    let paths = podcast
+                        .paths_as(&name, &global_config)
+                        .and_then(|old| Ok((old, podcast.paths_as(&new_name, &global_config)?)));
+                    ui.complete();
+
+                    let (old_paths, new_paths) = paths?;
+                    let mut renamed = RenamedPaths::default();
+                    for (old, new) in old_paths.into_iter().zip(new_paths) {
+                        renamed.files.insert((old.download_dir, new.download_dir));
+                        renamed.files.insert((old.tracker, new.tracker));
+                        if let (Some(old), Some(new)) = (old.symlink_dir, new.symlink_dir) {
+                            renamed.symlink_dirs.insert((old, new));
+                        }
+                    }
+                    Ok(renamed)
+                }
+            }
+        };
+
+        let (results, _) = podcasts
+            .for_each_podcast(global_config, fetch_mode, action)
+            .await?;
+
+        match results.into_iter().next() {
+            Some((_, Ok(Ok(renamed)))) => Ok(renamed),
+            Some((_, Ok(Err(e)) | Err(e))) => Err(e),
+            None => Err(Error::Usage(format!("'{}' doesn't exist!", name))),
+        }
     }
 
     /// Appends the `podcast.toml` file with the given podcast.
     ///
     /// If a podcast with the same name already exist,
     /// it does nothing and will return false. Otherwise true.
-    pub fn push(name: String, podcast: PodcastConfig) -> bool {
-        let mut podcasts = Self::load();
+    pub fn push(name: String, podcast: PodcastConfig) -> Result<bool, Error> {
+        let mut podcasts = Self::load()?;
         if podcasts.0.contains_key(&name) {
-            false
+            Ok(false)
         } else {
             podcasts.0.insert(name, podcast);
-            podcasts.save_to_file();
+            podcasts.save_to_file()?;
 
-            true
+            Ok(true)
         }
     }
 
     pub fn path() -> PathBuf {
         let path = utils::config_dir().join("podcasts.toml");
 
+        // Failing to create it is reported once it's read.
         if !path.exists() {
-            std::fs::File::create(&path).expect("failed to create podcasts.toml file");
+            let _ = fs::File::create(&path);
         }
 
         path
     }
 
-    pub fn into_outlines(self) -> Vec<opml::Outline> {
+    pub(crate) fn into_outlines(self) -> Vec<opml::Outline> {
         self.0
             .into_iter()
             .map(|(name, pod)| opml::Outline {
@@ -831,9 +1431,74 @@ pub struct PodcastConfig {
     download_hook: ConfigOption<PathBuf>,
     tracker_path: ConfigOption<String>,
     symlink: Option<String>,
+    transcripts: ConfigOption<TranscriptSettings>,
+    update_feed_url: Option<bool>,
+    strict_patterns: Option<bool>,
+    retries: Option<u32>,
+    retry_backoff_ms: Option<u64>,
+    retry_max_delay_ms: Option<u64>,
+    parallel_episodes: Option<usize>,
+    max_download_rate: Option<String>,
 }
 
 impl PodcastConfig {
+    /// Finds the invalid settings of the podcast, including the ones that
+    /// [`DownloadMode::new`] would reject.
+    fn check(&self, name: &str) -> Vec<Diagnostic> {
+        let mut diagnostics = Diagnostics::podcast(name);
+
+        if let Err(e) = reqwest::Url::parse(&self.url) {
+            diagnostics.error("url", format!("invalid url \"{}\": {}", &self.url, e));
+        }
+
+        diagnostics.check("name_pattern", self.name_pattern.as_ref(), check::pattern);
+        diagnostics.check("id_pattern", self.id_pattern.as_ref(), check::pattern);
+        diagnostics.check("download_path", self.download_path.as_ref(), check::pattern);
+        diagnostics.check("partial_path", self.partial_path.as_ref(), check::pattern);
+        diagnostics.check("tracker_path", self.tracker_path.enabled(), check::pattern);
+        diagnostics.check("symlink", self.symlink.as_ref(), check::pattern);
+        diagnostics.check("max_days", self.max_days.enabled(), check::non_negative);
+        diagnostics.check(
+            "max_episodes",
+            self.max_episodes.enabled(),
+            check::non_negative,
+        );
+        diagnostics.check("earliest_date", self.earliest_date.enabled(), check::date);
+        diagnostics.check("download_hook", self.download_hook.enabled(), check::hook);
+        diagnostics.check(
+            "max_download_rate",
+            self.max_download_rate.as_ref(),
+            check::rate,
+        );
+
+        match (&self.backlog_start, self.backlog_interval) {
+            (None, None) => {}
+            (Some(_), None) => diagnostics.error("backlog_start", "missing backlog_interval"),
+            (None, Some(_)) => diagnostics.error("backlog_interval", "missing backlog_start"),
+            (Some(start), Some(interval)) => {
+                if dateparser::parse(start).is_err() {
+                    diagnostics.error("backlog_start", format!("invalid date: \"{}\"", start));
+                }
+
+                if interval < 1 {
+                    diagnostics.error("backlog_interval", "must be at least 1 day");
+                }
+
+                for (field, enabled) in [
+                    ("max_days", self.max_days.is_enabled()),
+                    ("max_episodes", self.max_episodes.is_enabled()),
+                    ("earliest_date", self.earliest_date.is_enabled()),
+                ] {
+                    if enabled {
+                        diagnostics.error(field, "not compatible with backlog mode");
+                    }
+                }
+            }
+        }
+
+        diagnostics.into_inner()
+    }
+
     pub fn new(url: String) -> Self {
         Self {
             url,
@@ -850,9 +1515,77 @@ impl PodcastConfig {
             tracker_path: Default::default(),
             symlink: Default::default(),
             partial_path: Default::default(),
+            transcripts: Default::default(),
+            update_feed_url: Default::default(),
+            strict_patterns: Default::default(),
+            retries: Default::default(),
+            retry_backoff_ms: Default::default(),
+            retry_max_delay_ms: Default::default(),
+            parallel_episodes: Default::default(),
+            max_download_rate: Default::default(),
+        }
+    }
+
+    /// A rate limiter shared by the downloads of this podcast only.
+    ///
+    /// The global `max_download_rate` still limits the combined rate of all podcasts.
+    pub(crate) fn rate_limiter(
+        &self,
+        global_config: &GlobalConfig,
+    ) -> Result<Option<RateLimiter>, Error> {
+        global_config.rate_limiter(self.max_download_rate.as_deref())
+    }
+
+    /// How many episodes of the podcast are downloaded at the same time.
+    pub(crate) fn parallel_episodes(&self, global_config: &GlobalConfig) -> usize {
+        self.parallel_episodes
+            .or(global_config.parallel_episodes)
+            .unwrap_or(1)
+            .max(1)
+    }
+
+    /// How failed feed and enclosure downloads are retried.
+    pub(crate) fn retry_policy(&self, global_config: &GlobalConfig) -> RetryPolicy {
+        let default = RetryPolicy::default();
+        let millis = |podcast: Option<u64>, global: Option<u64>, default: time::Duration| {
+            podcast
+                .or(global)
+                .map(time::Duration::from_millis)
+                .unwrap_or(default)
+        };
+
+        RetryPolicy {
+            retries: self
+                .retries
+                .or(global_config.retries)
+                .unwrap_or(default.retries),
+            backoff: millis(
+                self.retry_backoff_ms,
+                global_config.retry_backoff_ms,
+                default.backoff,
+            ),
+            max_delay: millis(
+                self.retry_max_delay_ms,
+                global_config.retry_max_delay_ms,
+                default.max_delay,
+            ),
         }
     }
 
+    /// Whether to update the url when the feed has moved, or only warn about it.
+    fn update_feed_url(&self, global_config: &GlobalConfig) -> bool {
+        self.update_feed_url
+            .or(global_config.update_feed_url)
+            .unwrap_or(true)
+    }
+
+    /// Whether a pattern with a missing value is an error, instead of a placeholder.
+    fn strict_patterns(&self, global_config: &GlobalConfig) -> bool {
+        self.strict_patterns
+            .or(global_config.strict_patterns)
+            .unwrap_or(false)
+    }
+
     /// Changes the `earliest_date` setting to the current time.
     ///
     /// This means only episodes published after this function was called will be downloaded.
@@ -900,3 +1633,162 @@ impl LogConfig {
         self == &Self::default()
     }
 }
+
+/// The paths of a podcast's episodes paired with where they'd be under another name.
+#[derive(Debug, Default)]
+struct RenamedPaths {
+    /// Download directories and trackers.
+    files: BTreeSet<(PathBuf, PathBuf)>,
+    symlink_dirs: BTreeSet<(PathBuf, PathBuf)>,
+}
+
+impl RenamedPaths {
+    /// Ordered so that directories come before the files inside of them.
+    fn all(&self) -> impl Iterator<Item = &(PathBuf, PathBuf)> {
+        self.files.union(&self.symlink_dirs)
+    }
+}
+
+/// Deletes the paths that are specific to the podcast, after asking for confirmation.
+fn delete_podcast_files(name: &str, paths: &RenamedPaths) -> Result<(), Error> {
+    let mut deleted = vec![];
+    for (path, other_path) in paths.all() {
+        if path == other_path {
+            if path.exists() {
+                eprintln!("not deleting {:?} as it's not specific to '{}'", path, name);
+            }
+            continue;
+        }
+
+        // Trackers inside of a download directory are deleted along with it.
+        if path.exists() && !deleted.iter().any(|dir| path.starts_with(dir)) {
+            check_deletable(path)?;
+            deleted.push(path.clone());
+        }
+    }
+
+    if !deleted.is_empty() {
+        eprintln!("the following will be deleted:");
+        for path in &deleted {
+            eprintln!("  {}", path.display());
+        }
+
+        let answer = utils::get_input(Some("delete them? [y/N] "));
+        if !answer.is_some_and(|answer| answer.eq_ignore_ascii_case("y")) {
+            return Err(Error::Usage(format!("not removing '{}'", name)));
+        }
+    }
+
+    for path in deleted {
+        let result = if path.is_dir() {
+            fs::remove_dir_all(&path)
+        } else {
+            fs::remove_file(&path)
+        };
+
+        result.map_err(Error::io(&path))?;
+        eprintln!("deleted {:?}", path);
+    }
+
+    Ok(())
+}
+
+/// Fails for directories that hold more than a podcast, like the home directory.
+fn check_deletable(path: &Path) -> Result<(), Error> {
+    let protected = [
+        dirs::home_dir(),
+        Some(utils::config_dir()),
+        Some(utils::cache_dir()),
+    ];
+
+    if path.parent().is_none() || protected.iter().flatten().any(|dir| dir.starts_with(path)) {
+        return Err(Error::Usage(format!("refusing to delete {:?}", path)));
+    }
+
+    Ok(())
+}
+
+/// Moves every path, or moves back the ones that were moved if any of them fails.
+fn move_all(moves: &[&(PathBuf, PathBuf)]) -> Result<(), Error> {
+    let mut moved = vec![];
+
+    for (from, to) in moves {
+        // Already moved along with its directory.
+        if !from.exists() {
+            continue;
+        }
+
+        let result = match to.parent() {
+            Some(parent) => utils::create_dir(parent),
+            None => Ok(()),
+        }
+        .and_then(|_| fs::rename(from, to).map_err(Error::io(from)));
+
+        if let Err(e) = result {
+            for (from, to) in moved.into_iter().rev() {
+                if let Err(e) = fs::rename(to, from) {
+                    eprintln!("failed to move {:?} back to {:?}: {}", to, from, e);
+                }
+            }
+            return Err(e);
+        }
+
+        eprintln!("moved {:?} to {:?}", from, to);
+        moved.push((from, to));
+    }
+
+    Ok(())
+}
+
+/// Points the symlinks in the directory that point into a moved path to where it was moved.
+fn relink(dir: &Path, moves: &[&(PathBuf, PathBuf)]) -> std::io::Result<()> {
+    if !dir.is_dir() {
+        return Ok(());
+    }
+
+    for entry in fs::read_dir(dir)? {
+        let link = entry?.path();
+        let Ok(target) = fs::read_link(&link) else {
+            continue;
+        };
+
+        let moved = moves.iter().find_map(|(from, to)| {
+            let rest = target.strip_prefix(from).ok()?;
+            Some(to.join(rest))
+        });
+
+        if let Some(new_target) = moved {
+            fs::remove_file(&link)?;
+            std::os::unix::fs::symlink(new_target, &link)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn backlog_mode(interval: i64) -> Result<DownloadMode, Error> {
+        let config: PodcastConfig = toml::from_str(&format!(
+            "url = \"https://example.com/feed.xml\"\n\
+             backlog_start = \"2020-01-01\"\n\
+             backlog_interval = {}\n",
+            interval
+        ))
+        .unwrap();
+
+        DownloadMode::new(&GlobalConfig::default(), &config)
+    }
+
+    #[test]
+    fn backlog_interval_must_be_positive() {
+        assert!(matches!(
+            backlog_mode(7),
+            Ok(DownloadMode::Backlog { interval, .. }) if interval == Unix::from_secs(7 * 86400)
+        ));
+        assert!(matches!(backlog_mode(0), Err(Error::Config(_))));
+        assert!(matches!(backlog_mode(-1), Err(Error::Config(_))));
+    }
+}
@@ -1,38 +1,95 @@
 use crate::config::IndicatifSettings;
 use crate::episode::Episode;
+use crate::events::EventSink;
+use crate::events::JsonEvents;
 use crate::utils;
 use indicatif::MultiProgress;
 use indicatif::ProgressBar;
 use indicatif::ProgressStyle;
+use serde::Deserialize;
+use serde::Serialize;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 
-#[derive(Debug)]
+/// How the progress of a sync is shown.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum ProgressFormat {
+    /// Progress bars in the terminal.
+    #[default]
+    Bars,
+    /// One JSON event per line, for wrapping talecast in other programs.
+    Json,
+}
+
+/// Shows the state of a podcast while it syncs.
+///
+/// Every method does nothing by default, so library users only need to implement the
+/// callbacks they care about.
+pub trait ProgressBackend: Send + Sync {
+    /// Waiting for other podcasts to finish syncing.
+    fn waiting(&self) {}
+    fn fetching(&self) {}
+    fn init(&self) {}
+    fn begin_download(&self, _episode: &Episode, _index: usize, _episode_qty: usize) {}
+    /// Episodes are downloaded in parallel, so the progress is combined.
+    fn begin_parallel_download(&self, _episode_qty: usize) {}
+    fn progress(&self, _position: u64, _length: u64) {}
+    fn hook_status(&self) {}
+    fn error(&self, _msg: &str) {}
+    fn complete(&self, _error_qty: usize) {}
+}
+
+/// Creates the [`ProgressBackend`] of every podcast.
+pub enum ProgressOutput {
+    Bars {
+        mp: MultiProgress,
+        settings: Arc<IndicatifSettings>,
+        longest_podcast_name: usize,
+    },
+    Json(EventSink),
+}
+
+impl ProgressOutput {
+    pub fn backend(&self, podcast_name: &str) -> Box<dyn ProgressBackend> {
+        match self {
+            Self::Bars {
+                mp,
+                settings,
+                longest_podcast_name,
+            } => Box::new(Bars::new(
+                podcast_name.to_string(),
+                Arc::clone(settings),
+                mp,
+                *longest_podcast_name,
+            )),
+            Self::Json(sink) => Box::new(JsonEvents::new(podcast_name.to_string(), sink.clone())),
+        }
+    }
+}
+
+/// The progress and the logging of a single podcast.
 pub struct DownloadBar {
-    bar: Option<ProgressBar>,
+    backend: Box<dyn ProgressBackend>,
     podcast_name: String,
-    longest_podcast_name: usize,
-    settings: Arc<IndicatifSettings>,
+    position: AtomicU64,
+    length: AtomicU64,
     completed: bool,
 }
 
 impl DownloadBar {
-    pub fn new(
-        podcast_name: String,
-        settings: Arc<IndicatifSettings>,
-        mp: &MultiProgress,
-        longest_podcast_name: usize,
-    ) -> Self {
-        let bar = if settings.enabled() {
-            Some(mp.add(ProgressBar::new_spinner()))
-        } else {
-            None
-        };
+    pub fn new(podcast_name: String, output: &ProgressOutput) -> Self {
+        let backend = output.backend(&podcast_name);
+        Self::with_backend(podcast_name, backend)
+    }
 
+    pub fn with_backend(podcast_name: String, backend: Box<dyn ProgressBackend>) -> Self {
         Self {
-            bar,
-            settings,
+            backend,
             podcast_name,
-            longest_podcast_name,
+            position: AtomicU64::new(0),
+            length: AtomicU64::new(0),
             completed: false,
         }
     }
@@ -57,6 +114,117 @@ impl DownloadBar {
         log::error!("{}: {}", &self.podcast_name, msg.into());
     }
 
+    /// Waiting for other podcasts to finish syncing.
+    pub fn waiting(&self) {
+        self.backend.waiting();
+    }
+
+    pub fn fetching(&self) {
+        self.backend.fetching();
+    }
+
+    pub fn init(&self) {
+        self.backend.init();
+    }
+
+    pub fn begin_download(&self, episode: &Episode, index: usize, episode_qty: usize) {
+        self.position.store(0, Ordering::Relaxed);
+        self.length.store(0, Ordering::Relaxed);
+        self.backend.begin_download(episode, index, episode_qty);
+    }
+
+    pub fn hook_status(&self) {
+        self.backend.hook_status();
+    }
+
+    pub fn init_download_bar(&self, start_point: u64, total_size: u64) {
+        self.position.store(start_point, Ordering::Relaxed);
+        self.length.store(total_size, Ordering::Relaxed);
+        self.backend.progress(start_point, total_size);
+    }
+
+    pub fn set_progress(&self, progress: u64) {
+        self.position.store(progress, Ordering::Relaxed);
+        let length = self.length.load(Ordering::Relaxed);
+        self.backend.progress(progress, length);
+    }
+
+    /// Shows the combined progress of episodes that are downloaded in parallel.
+    pub fn begin_parallel_download(&self, episode_qty: usize) {
+        self.position.store(0, Ordering::Relaxed);
+        self.length.store(0, Ordering::Relaxed);
+        self.backend.begin_parallel_download(episode_qty);
+    }
+
+    fn shift_progress(&self, position: i64, length: i64) {
+        let shift = |val: &AtomicU64, delta: i64| {
+            let prev = val
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |val| {
+                    Some(val.saturating_add_signed(delta))
+                })
+                .unwrap();
+            prev.saturating_add_signed(delta)
+        };
+
+        let length = shift(&self.length, length);
+        let position = shift(&self.position, position);
+        self.backend.progress(position, length);
+    }
+
+    pub fn error(&mut self, msg: &str) {
+        if self.completed {
+            return;
+        }
+
+        self.log_error(msg);
+        self.backend.error(msg);
+        self.completed = true;
+    }
+
+    /// Completes the bar after some of the episodes failed to download.
+    pub fn complete_with_errors(&mut self, error_qty: usize) {
+        if self.completed {
+            return;
+        }
+
+        self.backend.complete(error_qty);
+        self.completed = true;
+    }
+
+    pub fn complete(&mut self) {
+        self.complete_with_errors(0);
+    }
+}
+
+/// Progress bars in the terminal, or nothing if disabled in the style settings.
+struct Bars {
+    bar: Option<ProgressBar>,
+    podcast_name: String,
+    longest_podcast_name: usize,
+    settings: Arc<IndicatifSettings>,
+}
+
+impl Bars {
+    fn new(
+        podcast_name: String,
+        settings: Arc<IndicatifSettings>,
+        mp: &MultiProgress,
+        longest_podcast_name: usize,
+    ) -> Self {
+        let bar = if settings.enabled() {
+            Some(mp.add(ProgressBar::new_spinner()))
+        } else {
+            None
+        };
+
+        Self {
+            bar,
+            settings,
+            podcast_name,
+            longest_podcast_name,
+        }
+    }
+
     fn prefix(&self) -> String {
         let pad_len = self.longest_podcast_name + 2 - self.podcast_name.chars().count();
         let padding: String = std::iter::repeat(' ').take(pad_len).collect();
@@ -67,26 +235,38 @@ impl DownloadBar {
         format!("{}{}", self.prefix(), msg)
     }
 
-    pub fn fetching(&self) {
+    fn set_template(&self, style: &str) {
         if let Some(pb) = &self.bar {
-            let template = IndicatifSettings::podcast_fetch_template();
-            pb.set_style(ProgressStyle::default_bar().template(&template).unwrap());
+            pb.set_style(ProgressStyle::default_bar().template(style).unwrap());
+        }
+    }
 
-            let msg = self.prefix();
-            pb.set_message(msg);
+    fn spin(&self, template: &str) {
+        if let Some(pb) = &self.bar {
+            self.set_template(template);
+            pb.set_message(self.prefix());
             pb.enable_steady_tick(self.settings.spinner_speed());
         }
     }
+}
 
-    pub fn init(&self) {
+impl ProgressBackend for Bars {
+    fn waiting(&self) {
+        self.spin(&IndicatifSettings::podcast_waiting_template());
+    }
+
+    fn fetching(&self) {
+        self.spin(&IndicatifSettings::podcast_fetch_template());
+    }
+
+    fn init(&self) {
         if let Some(pb) = &self.bar {
-            let template = self.settings.download_template();
-            pb.set_style(ProgressStyle::default_bar().template(&template).unwrap());
+            self.set_template(&self.settings.download_template());
             pb.enable_steady_tick(self.settings.spinner_speed());
         }
     }
 
-    pub fn begin_download(&self, episode: &Episode, index: usize, episode_qty: usize) {
+    fn begin_download(&self, episode: &Episode, index: usize, episode_qty: usize) {
         if let Some(pb) = &self.bar {
             let fitted_episode_title = {
                 let title_length = self.settings.title_length();
@@ -104,59 +284,86 @@ impl DownloadBar {
             );
 
             pb.set_message(msg);
-            pb.set_position(0);
         }
     }
 
-    pub fn set_template(&self, style: &str) {
+    fn begin_parallel_download(&self, episode_qty: usize) {
         if let Some(pb) = &self.bar {
-            pb.set_style(ProgressStyle::default_bar().template(style).unwrap());
+            let msg = format!(
+                "{:<podcast_width$} {} episodes ",
+                &self.podcast_name,
+                episode_qty,
+                podcast_width = self.longest_podcast_name + 3
+            );
+
+            pb.set_message(msg);
         }
     }
 
-    pub fn hook_status(&self) {
-        let template = self.settings.hook_template();
-        self.set_template(&template);
+    fn progress(&self, position: u64, length: u64) {
+        if let Some(pb) = &self.bar {
+            pb.set_length(length);
+            pb.set_position(position);
+        }
     }
 
-    pub fn init_download_bar(&self, start_point: u64, total_size: u64) {
+    fn hook_status(&self) {
+        self.set_template(&self.settings.hook_template());
+    }
+
+    fn error(&self, msg: &str) {
         if let Some(pb) = &self.bar {
-            pb.set_length(total_size);
-            pb.set_position(start_point);
+            self.set_template(&self.settings.error_template());
+            pb.finish_with_message(self.msg_with_prefix(msg));
         }
     }
 
-    pub fn set_progress(&self, progress: u64) {
+    fn complete(&self, error_qty: usize) {
         if let Some(pb) = &self.bar {
-            pb.set_position(progress);
+            if error_qty == 0 {
+                self.set_template(&self.settings.completion_template());
+                pb.finish_with_message(self.podcast_name.clone());
+            } else {
+                self.set_template(&self.settings.partial_template());
+                let errors = if error_qty == 1 { "error" } else { "errors" };
+                let msg = format!("completed with {} {}", error_qty, errors);
+                pb.finish_with_message(self.msg_with_prefix(&msg));
+            }
         }
     }
+}
 
-    pub fn error(&mut self, msg: &str) {
-        if self.completed {
-            return;
-        }
+/// The progress of a single download on a [`DownloadBar`].
+///
+/// The bar might be shared by episodes downloading in parallel, so the download
+/// only adds its own share of bytes to the bar.
+pub struct Progress<'a> {
+    ui: &'a DownloadBar,
+    position: AtomicU64,
+    length: AtomicU64,
+}
 
-        if let Some(pb) = &self.bar {
-            self.log_error(msg);
-            let template = self.settings.error_template();
-            self.set_template(&template);
-            let msg = self.msg_with_prefix(msg);
-            pb.finish_with_message(msg);
-            self.completed = true;
+impl<'a> Progress<'a> {
+    pub fn new(ui: &'a DownloadBar) -> Self {
+        Self {
+            ui,
+            position: AtomicU64::new(0),
+            length: AtomicU64::new(0),
         }
     }
 
-    pub fn complete(&mut self) {
-        if self.completed {
-            return;
-        }
+    /// Can be called again when a download is retried, only the difference is added to the bar.
+    pub fn init(&self, start_point: u64, total_size: u64) {
+        let position = self.position.swap(start_point, Ordering::Relaxed);
+        let length = self.length.swap(total_size, Ordering::Relaxed);
+        self.ui.shift_progress(
+            start_point as i64 - position as i64,
+            total_size as i64 - length as i64,
+        );
+    }
 
-        if let Some(pb) = &self.bar {
-            let template = self.settings.completion_template();
-            self.set_template(&template);
-            pb.finish_with_message(self.podcast_name.clone());
-            self.completed = true;
-        }
+    pub fn set(&self, progress: u64) {
+        let position = self.position.swap(progress, Ordering::Relaxed);
+        self.ui.shift_progress(progress as i64 - position as i64, 0);
     }
 }
@@ -1,8 +1,13 @@
 use crate::episode::DownloadedEpisode;
+use crate::episode::Episode;
 use crate::utils;
 use std::collections::HashSet;
 use std::fs;
 use std::path::Path;
+use std::sync::Mutex;
+
+/// Serializes appends to tracker files, as episodes might be downloaded in parallel.
+static TRACKER_LOCK: Mutex<()> = Mutex::new(());
 
 /// Keeps track of which episodes have already been downloaded.
 #[derive(Debug, Default)]
@@ -13,6 +18,15 @@ impl DownloadedEpisodes {
         self.0.contains(episode_id)
     }
 
+    /// Transcripts are tracked on their own line as they might be fetched after the episode.
+    fn transcript_id(episode_id: &str) -> String {
+        format!("{}::transcript", episode_id)
+    }
+
+    pub fn contains_transcript(&self, episode_id: &str) -> bool {
+        self.0.contains(&Self::transcript_id(episode_id))
+    }
+
     pub fn load(path: &Path) -> Self {
         let s = match fs::read_to_string(path) {
             Ok(s) => s,
@@ -34,33 +48,45 @@ impl DownloadedEpisodes {
         Self(hashmap)
     }
 
-    pub fn append(path: &Path, id: &str, episode: &DownloadedEpisode) -> Result<(), String> {
+    pub(crate) fn append(path: &Path, id: &str, episode: &DownloadedEpisode) -> Result<(), String> {
+        Self::append_line(path, id, episode.inner().attrs.title())
+    }
+
+    pub(crate) fn append_transcript(
+        path: &Path,
+        id: &str,
+        episode: &Episode,
+    ) -> Result<(), String> {
+        Self::append_line(path, &Self::transcript_id(id), episode.attrs.title())
+    }
+
+    fn append_line(path: &Path, id: &str, title: &str) -> Result<(), String> {
         use std::io::Write;
 
         if path.is_dir() {
-            eprintln!("error: invalid download tracker path: {:?}", path);
-            eprintln!("download tracker cannot point to a directory");
-            std::process::exit(1);
+            return Err(format!(
+                "invalid download tracker path: {:?}, it cannot point to a directory",
+                path
+            ));
         }
 
         if let Some(parent) = path.parent() {
-            utils::create_dir(&parent)
+            utils::create_dir(parent).map_err(|e| e.to_string())?;
         }
 
+        // Written in one go so that lines from concurrent appends can't interleave.
+        let line = format!("{} {} \"{}\"\n", id, utils::current_unix().as_secs(), title);
+
+        let _lock = TRACKER_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
         let mut file = fs::OpenOptions::new()
             .append(true)
             .create(true)
             .open(path)
             .map_err(|_| "failed to open tracker file".to_string())?;
 
-        writeln!(
-            file,
-            "{} {} \"{}\"",
-            id,
-            utils::current_unix().as_secs(),
-            episode.inner().attrs.title()
-        )
-        .unwrap();
+        file.write_all(line.as_bytes())
+            .map_err(|_| "failed to write to tracker file".to_string())?;
 
         Ok(())
     }
@@ -1,8 +1,18 @@
 use crate::cache;
+use crate::chapters::Chapters;
 use crate::config::Config;
 use crate::config::DownloadMode;
 use crate::display::DownloadBar;
+use crate::display::Progress;
 use crate::download_tracker::DownloadedEpisodes;
+use crate::http::{self, header, HttpClient, Request, StatusCode};
+use crate::limits::ConnectionLimits;
+use crate::report::EpisodeListing;
+use crate::report::Record;
+use crate::retry;
+use crate::retry::RequestError;
+use crate::throttle::Throttle;
+use crate::transcript::Transcript;
 use crate::utils;
 use futures_util::StreamExt;
 use std::cmp;
@@ -31,6 +41,11 @@ pub trait XmlWrapper {
         }
     }
 
+    /// The text of the tag, including tags that were parsed as numbers.
+    fn get_text(&self, key: &str) -> Option<String> {
+        utils::val_to_string(self.inner().get(key)?)
+    }
+
     fn get_val(&self, key: &str) -> Result<&serde_json::Value, String> {
         self.inner()
             .get(key)
@@ -100,6 +115,13 @@ impl Attributes {
         self.published
     }
 
+    /// The publish date in RFC 3339 format.
+    pub fn pubdate(&self) -> String {
+        chrono::DateTime::from_timestamp(self.published.as_secs() as i64, 0)
+            .map(|date| date.to_rfc3339())
+            .unwrap_or_default()
+    }
+
     pub fn _mime(&self) -> Option<&str> {
         self.mime.as_deref()
     }
@@ -120,6 +142,10 @@ impl Attributes {
         self.raw.get_str(key)
     }
 
+    pub fn get_text(&self, key: &str) -> Option<String> {
+        self.raw.get_text(key)
+    }
+
     pub fn image(&self) -> Result<&str, String> {
         let key = "itunes:image";
         self.raw.get_url(key)
@@ -132,28 +158,63 @@ impl Attributes {
         self.get_str("description")
     }
 
-    pub fn itunes_episode(&self) -> Result<&str, String> {
-        let key = "itunes:episode";
-        self.get_str(&key)
+    /// The `<itunes:season>` number.
+    pub fn season(&self) -> Option<u32> {
+        self.get_text("itunes:season")?.trim().parse().ok()
+    }
+
+    /// The `<itunes:episode>` number.
+    pub fn episode_number(&self) -> Option<u32> {
+        self.get_text("itunes:episode")?.trim().parse().ok()
+    }
+
+    /// The `<itunes:duration>`, in any of the formats feeds use for it.
+    pub fn duration(&self) -> Option<time::Duration> {
+        utils::parse_duration(&self.get_text("itunes:duration")?)
     }
 
-    pub fn itunes_duration(&self) -> Result<&str, String> {
-        let key = "itunes:duration";
-        self.get_str(&key)
+    /// The `<itunes:episodeType>`, like `full`, `trailer` or `bonus`.
+    pub fn episode_type(&self) -> Option<String> {
+        let ty = self.get_text("itunes:episodeType")?;
+        Some(ty.trim().to_lowercase()).filter(|ty| !ty.is_empty())
+    }
+
+    /// The extension of the enclosure, from its url or else its mime type.
+    pub fn extension(&self) -> Option<String> {
+        utils::extension_from_url(self.url())
+            .or_else(|| utils::extension_from_mime(self.mime.as_deref()?))
+    }
+
+    /// The url to the podcasting 2.0 JSON chapters file.
+    pub fn chapters(&self) -> Result<&str, String> {
+        let key = "podcast:chapters";
+        self.raw.get_url(key)
+    }
+
+    /// The podcasting 2.0 transcripts of the episode that are in a supported format.
+    pub fn transcripts(&self) -> Vec<Transcript> {
+        let key = "podcast:transcript";
+        match self.raw.get_val(key) {
+            Ok(serde_json::Value::Array(vals)) => {
+                vals.iter().filter_map(Transcript::from_val).collect()
+            }
+            Ok(val) => Transcript::from_val(val).into_iter().collect(),
+            Err(_) => vec![],
+        }
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct Episode {
-    pub config: Config,
-    pub tags: Option<id3::Tag>,
-    pub index: usize,
-    pub attrs: Attributes,
-    pub image_url: Option<String>,
+    pub(crate) config: Config,
+    pub(crate) tags: Option<id3::Tag>,
+    pub(crate) index: usize,
+    pub(crate) attrs: Attributes,
+    pub(crate) image_url: Option<String>,
 }
 
 impl Episode {
-    pub fn new(
+    pub(crate) fn new(
         attrs: Attributes,
         index: usize,
         config: Config,
@@ -169,67 +230,103 @@ impl Episode {
         }
     }
 
+    pub fn title(&self) -> &str {
+        self.attrs.title()
+    }
+
+    pub fn guid(&self) -> &str {
+        self.attrs.guid()
+    }
+
+    pub fn url(&self) -> &str {
+        self.attrs.url()
+    }
+
+    /// When the episode was published, as time since the Unix epoch.
+    pub fn published(&self) -> time::Duration {
+        self.attrs.published()
+    }
+
+    /// Position of the episode in the feed, from oldest to newest starting at 0.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// The evaluated `name_pattern`, which the episode's file is named after.
+    pub fn file_name(&self) -> &str {
+        &self.config.name_pattern
+    }
+
+    /// The evaluated `download_path`.
+    pub fn download_dir(&self) -> &Path {
+        &self.config.download_path
+    }
+
+    /// The evaluated `symlink`, if the podcast has one.
+    pub fn symlink_dir(&self) -> Option<&Path> {
+        self.config.symlink.as_deref()
+    }
+
+    /// The evaluated `tracker_path`, the file that tracks the downloaded episodes.
+    pub fn tracker_path(&self) -> &Path {
+        self.config.tracker_path.as_path()
+    }
+
     const TITLELEN: usize = 30;
 
-    pub fn _log_error(&self, ui: &DownloadBar, msg: impl Into<String>) {
+    pub(crate) fn log_error(&self, ui: &DownloadBar, msg: impl Into<String>) {
         let ep_name = utils::truncate_string(self.attrs.title(), Self::TITLELEN, true);
         let msg = format!("{}: {}", ep_name, msg.into());
         ui.log_error(msg);
     }
 
-    pub fn log_warn(&self, ui: &DownloadBar, msg: impl Into<String>) {
+    pub(crate) fn log_warn(&self, ui: &DownloadBar, msg: impl Into<String>) {
         let ep_name = utils::truncate_string(self.attrs.title(), Self::TITLELEN, true);
         let msg = format!("{}: {}", ep_name, msg.into());
         ui.log_warn(msg);
     }
 
-    pub fn log_trace(&self, ui: &DownloadBar, msg: impl Into<String>) {
+    pub(crate) fn log_trace(&self, ui: &DownloadBar, msg: impl Into<String>) {
         let ep_name = utils::truncate_string(self.attrs.title(), Self::TITLELEN, true);
         let msg = format!("{}: {}", ep_name, msg.into());
         ui.log_trace(msg);
     }
 
-    pub fn log_debug(&self, ui: &DownloadBar, msg: impl Into<String>) {
+    pub(crate) fn log_debug(&self, ui: &DownloadBar, msg: impl Into<String>) {
         let ep_name = utils::truncate_string(self.attrs.title(), Self::TITLELEN, true);
         let msg = format!("{}: {}", ep_name, msg.into());
         ui.log_debug(msg);
     }
 
-    fn is_downloaded(&self) -> bool {
+    pub fn is_downloaded(&self) -> bool {
         let id = self.get_id();
         let path = self.tracker_path();
         DownloadedEpisodes::load(&path).contains_episode(&id)
     }
 
-    pub fn should_download(&self, mode: &DownloadMode, episode_qty: usize) -> bool {
-        let passed_filter = match mode {
-            DownloadMode::Backlog { start, interval } => {
-                let time_passed = utils::current_unix() - *start;
-                let intervals_passed = time_passed.as_secs() / interval.as_secs();
-                intervals_passed >= self.index as u64
-            }
-
-            DownloadMode::Standard {
-                max_time,
-                max_episodes,
-                earliest_date,
-            } => {
-                let max_time_exceeded = max_time.map_or(false, |max_time| {
-                    (utils::current_unix() - self.attrs.published) > max_time
-                });
-
-                let max_episodes_exceeded = max_episodes.map_or(false, |max_episodes| {
-                    (episode_qty - max_episodes as usize) > self.index
-                });
+    fn is_transcript_downloaded(&self) -> bool {
+        let id = self.get_id();
+        let path = self.tracker_path();
+        DownloadedEpisodes::load(path).contains_transcript(&id)
+    }
 
-                let episode_too_old =
-                    earliest_date.map_or(false, |date| date > self.attrs.published);
+    pub(crate) fn should_download(&self, mode: &DownloadMode, episode_qty: usize) -> bool {
+        self.passes_filter(mode, episode_qty) && !self.is_downloaded()
+    }
 
-                !max_time_exceeded && !max_episodes_exceeded && !episode_too_old
-            }
-        };
+    /// Whether the episode was downloaded without a transcript that should have been fetched.
+    ///
+    /// This happens when the transcript is published after the episode, or when transcripts
+    /// are enabled after the episode was downloaded.
+    pub(crate) fn is_missing_transcript(&self, mode: &DownloadMode, episode_qty: usize) -> bool {
+        self.transcript().is_some()
+            && self.passes_filter(mode, episode_qty)
+            && self.is_downloaded()
+            && !self.is_transcript_downloaded()
+    }
 
-        passed_filter && !self.is_downloaded()
+    fn passes_filter(&self, mode: &DownloadMode, episode_qty: usize) -> bool {
+        mode.includes(self.attrs.published, self.index, episode_qty)
     }
 
     /// Filename of episode when it's being downloaded.
@@ -242,73 +339,248 @@ impl Episode {
         self.config.id_pattern.replace(" ", "_")
     }
 
-    fn tracker_path(&self) -> &Path {
-        self.config.tracker_path.as_path()
-    }
-
     fn into_downloaded(&self, path: PathBuf) -> DownloadedEpisode<'_> {
         DownloadedEpisode::new(self, path)
     }
 
-    pub async fn download<'a>(
+    /// Path in the download directory for a file named after the `name_pattern`.
+    fn named_path(&self, extension: Option<&str>) -> PathBuf {
+        let mut new_name = sanitize_filename::sanitize(&self.config.name_pattern);
+        let path = self.config.download_path.join(&new_name);
+
+        match extension {
+            Some(extension) => {
+                let max_file_len: usize = 255;
+                let ext_len = extension.len() + 1; // + 1 for the dot.
+                let overflow = (new_name.len() + ext_len).saturating_sub(max_file_len);
+                for _ in 0..overflow {
+                    new_name.pop();
+                }
+
+                let mut new_path = path.with_file_name(new_name);
+                new_path.set_extension(extension);
+                new_path
+            }
+            None => path,
+        }
+    }
+
+    pub(crate) fn listing(
+        &self,
+        podcast: &str,
+        mode: &DownloadMode,
+        episode_qty: usize,
+    ) -> EpisodeListing {
+        let downloaded = self.is_downloaded();
+
+        EpisodeListing {
+            podcast: podcast.to_string(),
+            index: self.index + 1,
+            pubdate: self.attrs.pubdate(),
+            title: self.attrs.title().to_string(),
+            duration: self.attrs.duration().map(utils::format_duration),
+            guid: self.attrs.guid().to_string(),
+            downloaded,
+            pending: !downloaded && self.passes_filter(mode, episode_qty),
+        }
+    }
+
+    pub(crate) fn error_record(&self, podcast: &str, error: String) -> Record {
+        Record::EpisodeError {
+            podcast: podcast.to_string(),
+            title: self.attrs.title().to_string(),
+            guid: self.attrs.guid().to_string(),
+            error,
+        }
+    }
+
+    /// Describes what downloading the episode would do, for dry runs.
+    pub fn plan(&self) -> String {
+        let extension = self.attrs.extension();
+        let extension = extension.as_deref().unwrap_or(utils::DEFAULT_EXTENSION);
+        let path = self.named_path(Some(extension));
+
+        let mut lines = vec![
+            format!("  {}", self.attrs.title()),
+            format!("    url:        {}", self.attrs.url()),
+            format!("    path:       {}", path.display()),
+            format!(
+                "    tracker:    {} ({})",
+                self.tracker_path().display(),
+                self.get_id()
+            ),
+        ];
+
+        if let Some(symlink) = self.config.symlink.as_ref() {
+            let file_name = path.file_name().unwrap_or_default();
+            lines.push(format!(
+                "    symlink:    {}",
+                symlink.join(file_name).display()
+            ));
+        }
+
+        lines.extend(self.plan_transcript());
+        lines.join("\n")
+    }
+
+    /// Describes where the transcript would be downloaded to, for dry runs.
+    pub(crate) fn plan_transcript(&self) -> Option<String> {
+        let transcript = self.transcript()?;
+        let path = self.named_path(Some(transcript.ty.extension()));
+        Some(format!("    transcript: {}", path.display()))
+    }
+
+    /// The transcript to download, if transcripts are enabled.
+    fn transcript(&self) -> Option<Transcript> {
+        let settings = self.config.transcripts.as_ref()?;
+        Transcript::select(self.attrs.transcripts(), settings.types())
+    }
+
+    /// Downloads the transcript next to the episode and returns it along with its contents.
+    ///
+    /// Failing to fetch a transcript doesn't fail the episode, it'll be retried on the next sync.
+    pub(crate) async fn download_transcript(
+        &self,
+        client: &dyn HttpClient,
+        ui: &DownloadBar,
+    ) -> Option<(Transcript, String)> {
+        let transcript = self.transcript()?;
+        let path = self.named_path(Some(transcript.ty.extension()));
+        if let Err(e) = utils::create_dir(&self.config.download_path) {
+            self.log_warn(ui, format!("failed to download transcript: {}", e));
+            return None;
+        }
+
+        self.log_debug(ui, "downloading transcript");
+        let text = match transcript.download(client, &path, ui).await {
+            Ok(text) => text,
+            Err(e) => {
+                self.log_warn(ui, format!("failed to download transcript: {}", e));
+                return None;
+            }
+        };
+
+        if let Err(e) =
+            DownloadedEpisodes::append_transcript(self.tracker_path(), &self.get_id(), self)
+        {
+            self.log_warn(ui, format!("failed to track transcript: {}", e));
+        }
+
+        Some((transcript, text))
+    }
+
+    pub(crate) async fn download<'a>(
         &'a self,
-        client: &reqwest::Client,
+        client: &dyn HttpClient,
+        limits: &ConnectionLimits,
+        throttle: &Throttle,
         ui: &DownloadBar,
     ) -> Result<DownloadedEpisode<'a>, String> {
         self.log_debug(ui, "downloading episode");
-        let audio_file = self.download_enclosure(client, ui).await?;
+        let audio_file = self
+            .download_enclosure(client, limits, throttle, ui)
+            .await?;
         let mut episode = self.into_downloaded(audio_file);
-        episode.process(ui).await?;
+        episode.process(client, ui).await?;
         episode.run_download_hook(ui);
         episode.mark_downloaded()?;
         Ok(episode)
     }
 
-    async fn download_enclosure<'a>(
-        &'a self,
-        client: &reqwest::Client,
+    async fn download_enclosure(
+        &self,
+        client: &dyn HttpClient,
+        limits: &ConnectionLimits,
+        throttle: &Throttle,
         ui: &DownloadBar,
     ) -> Result<PathBuf, String> {
+        let progress = Progress::new(ui);
+
+        retry::with_retries(&self.config.retry, ui, || async {
+            // Released between attempts so others can use the host while we back off.
+            let _permit = limits.host_permit(self.url()).await;
+            self.try_download_enclosure(client, throttle, &progress, ui)
+                .await
+        })
+        .await
+    }
+
+    /// A single attempt at downloading the enclosure.
+    ///
+    /// Resumes from the `.partial` file of a previous attempt if the server supports it.
+    async fn try_download_enclosure(
+        &self,
+        client: &dyn HttpClient,
+        throttle: &Throttle,
+        progress: &Progress<'_>,
+        ui: &DownloadBar,
+    ) -> Result<PathBuf, RequestError> {
         let config = &self.config;
 
-        let partial_path = config
+        let partial_dir = config
             .partial_path
             .clone()
-            .unwrap_or_else(|| config.download_path.clone())
-            .join(self.partial_name());
+            .unwrap_or_else(|| config.download_path.clone());
+        for dir in [&partial_dir, &config.download_path] {
+            utils::create_dir(dir).map_err(|e| RequestError::fatal(e.to_string()))?;
+        }
+        let partial_path = partial_dir.join(self.partial_name());
 
         let mut file = fs::OpenOptions::new()
             .write(true)
             .create(true)
             .open(&partial_path)
-            .map_err(|_| "failed to write file".to_string())?;
+            .map_err(|_| RequestError::fatal("failed to write file"))?;
 
         let mut downloaded = file
             .seek(std::io::SeekFrom::End(0))
-            .map_err(|_| "file error".to_string())?;
+            .map_err(|_| RequestError::fatal("file error"))?;
+
+        self.log_trace(ui, format!("connecting to url: {:?}", self.url()));
+        let request = Request::get(self.url())
+            .map_err(RequestError::from_http)?
+            .header(header::RANGE, &format!("bytes={}-", downloaded));
+        let response = http::follow_redirects(client, request, |status, location| {
+            self.log_trace(ui, format!("redirected ({}) to: {}", status, location));
+        })
+        .await
+        .map_err(RequestError::from_http)?;
+
+        let status = response.status;
+        if status == StatusCode::RANGE_NOT_SATISFIABLE {
+            // The partial file doesn't match the enclosure anymore.
+            file.set_len(0)
+                .map_err(|_| RequestError::fatal("failed to truncate file"))?;
+            return Err(RequestError::transient(
+                "invalid partial file, restarting download",
+            ));
+        }
 
-        self.log_trace(ui, format!("connecting to url: {:?}", self.as_ref().url()));
-        let response = client
-            .get(self.as_ref().url())
-            .header(reqwest::header::RANGE, format!("bytes={}-", downloaded))
-            .send()
-            .await;
+        RequestError::from_status(&response)?;
 
-        let response = utils::short_handle_response(response)?;
+        if downloaded > 0 && status != StatusCode::PARTIAL_CONTENT {
+            self.log_debug(ui, "server doesn't support resuming, restarting download");
+            file.set_len(0)
+                .and_then(|_| file.seek(std::io::SeekFrom::Start(0)))
+                .map_err(|_| RequestError::fatal("failed to truncate file"))?;
+            downloaded = 0;
+        }
 
-        let total_size = response.content_length().unwrap_or(0);
-        let extension = utils::get_extension_from_response(&response, &self);
+        let total_size = downloaded + response.content_length().unwrap_or(0);
+        let extension = utils::get_extension_from_response(&response, &self)
+            .unwrap_or_else(|| utils::DEFAULT_EXTENSION.to_string());
 
-        ui.init_download_bar(downloaded, total_size);
+        progress.init(downloaded, total_size);
 
         let mut stream = response.bytes_stream();
 
         while let Some(item) = stream.next().await {
-            let chunk = item.map_err(|_| "failed to load chunk".to_string())?;
+            let chunk = item.map_err(RequestError::from_http)?;
+            throttle.consume(chunk.len() as u64).await;
             file.write_all(&chunk)
-                .map_err(|_| "failed to write chunk to file".to_string())?;
+                .map_err(|_| RequestError::fatal("failed to write chunk to file"))?;
             downloaded = cmp::min(downloaded + (chunk.len() as u64), total_size);
-            ui.set_progress(downloaded);
+            progress.set(downloaded);
         }
 
         let path = {
@@ -320,7 +592,8 @@ impl Episode {
             path
         };
 
-        fs::rename(partial_path, &path).map_err(|_| "failed to rename episode file".to_string())?;
+        fs::rename(partial_path, &path)
+            .map_err(|_| RequestError::fatal("failed to rename episode file"))?;
 
         Ok(path)
     }
@@ -330,8 +603,10 @@ pub struct DownloadedEpisode<'a> {
     inner: &'a Episode,
     /// Where the episode is downloaded.
     path: PathBuf,
-    /// The handle to the process of an optional post-download hook.
-    handle: Option<JoinHandle<()>>,
+    /// Where the episode is symlinked to, if configured.
+    symlink: Option<PathBuf>,
+    /// The handle to the process of an optional post-download hook, returning its exit code.
+    handle: Option<JoinHandle<Option<i32>>>,
 }
 
 impl<'a> DownloadedEpisode<'a> {
@@ -339,15 +614,35 @@ impl<'a> DownloadedEpisode<'a> {
         Self {
             inner,
             path,
+            symlink: None,
             handle: None,
         }
     }
 
-    pub fn into_path(self) -> PathBuf {
-        self.path
+    pub fn into_record(self, podcast: &str, hook_status: Option<i32>) -> Record {
+        let attrs = &self.inner.attrs;
+        let bytes = fs::metadata(&self.path).map(|meta| meta.len()).unwrap_or(0);
+
+        Record::Episode {
+            podcast: podcast.to_string(),
+            title: attrs.title().to_string(),
+            guid: attrs.guid().to_string(),
+            id: self.inner.get_id(),
+            pubdate: attrs.pubdate(),
+            url: attrs.url().to_string(),
+            path: self.path,
+            symlink: self.symlink,
+            bytes,
+            hook_status,
+        }
     }
 
     pub fn mark_downloaded(&self) -> Result<(), String> {
+        // Forced downloads might already be in the tracker.
+        if self.inner.is_downloaded() {
+            return Ok(());
+        }
+
         let id = self.inner.config.id_pattern.replace(" ", "_");
         let path = self.inner.config.tracker_path.as_path();
         DownloadedEpisodes::append(&path, &id, self)
@@ -361,9 +656,28 @@ impl<'a> DownloadedEpisode<'a> {
         &self.path
     }
 
-    pub async fn normalize_id3v2(&self, ui: &DownloadBar) {
+    fn is_mp3(&self) -> bool {
+        self.path.extension().is_some_and(|ext| ext == "mp3")
+    }
+
+    async fn fetch_chapters(&self, client: &dyn HttpClient, ui: &DownloadBar) -> Option<Chapters> {
+        if !self.is_mp3() {
+            return None;
+        }
+
+        let url = self.inner.attrs.chapters().ok()?;
+        Chapters::fetch(client, url, ui).await
+    }
+
+    pub async fn normalize_id3v2(
+        &self,
+        client: &dyn HttpClient,
+        chapters: Option<Chapters>,
+        transcript: Option<(Transcript, String)>,
+        ui: &DownloadBar,
+    ) {
         use id3::TagLike;
-        if self.path.extension().is_some_and(|ext| ext == "mp3") {
+        if self.is_mp3() {
             self.inner.log_trace(ui, "normalizing id3 tags");
             if let Some(xml_tags) = &self.inner.tags {
                 let mut file_tags = id3::Tag::read_from_path(&self.path()).unwrap_or_default();
@@ -385,8 +699,13 @@ impl<'a> DownloadedEpisode<'a> {
                     .any(|pic| pic.picture_type == id3::frame::PictureType::CoverFront)
                 {
                     if let Some(img_url) = self.inner.image_url.as_ref() {
-                        if let Some(frame) =
-                            cache::get_image(img_url, id3::frame::PictureType::CoverFront, ui).await
+                        if let Some(frame) = cache::get_image(
+                            client,
+                            img_url,
+                            id3::frame::PictureType::CoverFront,
+                            ui,
+                        )
+                        .await
                         {
                             file_tags.add_frame(frame);
                             self.inner
@@ -400,6 +719,34 @@ impl<'a> DownloadedEpisode<'a> {
                     }
                 }
 
+                if let Some(chapters) = chapters {
+                    if file_tags.chapters().next().is_none() {
+                        let duration = file_tags.duration();
+                        for frame in chapters.into_frames(client, duration, ui).await {
+                            file_tags.add_frame(frame);
+                        }
+                        self.inner
+                            .log_debug(ui, "added chapters to podcast episode");
+                    }
+                }
+
+                let embed_transcript = self
+                    .inner
+                    .config
+                    .transcripts
+                    .as_ref()
+                    .is_some_and(|settings| settings.embed());
+
+                if let Some((transcript, text)) = transcript.filter(|_| embed_transcript) {
+                    if file_tags.lyrics().next().is_none() {
+                        for frame in transcript.to_frames(&text) {
+                            file_tags.add_frame(frame);
+                        }
+                        self.inner
+                            .log_debug(ui, "embedded transcript in podcast episode");
+                    }
+                }
+
                 if let Err(e) = file_tags.write_to_path(&self.path(), id3::Version::Id3v24) {
                     ui.log_error(format!("failed to write tags to file: {:?}", e));
                 };
@@ -414,11 +761,11 @@ impl<'a> DownloadedEpisode<'a> {
         self.path.file_name().unwrap().to_str().unwrap()
     }
 
-    pub async fn await_handle(&mut self, ui: &DownloadBar) {
-        if let Some(handle) = self.handle.take() {
-            self.inner.log_debug(ui, "awaiting download hook");
-            let _ = handle.await;
-        }
+    /// Waits for the download hook to finish and returns its exit code.
+    pub async fn await_handle(&mut self, ui: &DownloadBar) -> Option<i32> {
+        let handle = self.handle.take()?;
+        self.inner.log_debug(ui, "awaiting download hook");
+        handle.await.ok().flatten()
     }
 
     fn run_download_hook(&mut self, ui: &DownloadBar) {
@@ -435,7 +782,8 @@ impl<'a> DownloadedEpisode<'a> {
             std::process::Command::new(script_path)
                 .arg(path)
                 .output()
-                .unwrap();
+                .ok()
+                .and_then(|output| output.status.code())
         });
 
         self.handle = Some(handle);
@@ -454,41 +802,38 @@ impl<'a> DownloadedEpisode<'a> {
                 return Err("configured symlink path is not a directory".to_string());
             }
 
-            std::os::unix::fs::symlink(self.path(), new_path)
-                .map_err(|_| "failed to create symlink".to_string())?;
+            // Left over from an earlier download of the episode, like with `--force`.
+            if let Ok(target) = fs::read_link(&new_path) {
+                if target != self.path() {
+                    fs::remove_file(&new_path)
+                        .map_err(|_| "failed to replace existing symlink".to_string())?;
+                }
+            }
+
+            if !new_path.is_symlink() {
+                std::os::unix::fs::symlink(self.path(), &new_path)
+                    .map_err(|_| "failed to create symlink".to_string())?;
+            }
+            self.symlink = Some(new_path);
         }
 
         Ok(())
     }
 
-    async fn process(&mut self, ui: &DownloadBar) -> Result<(), String> {
+    async fn process(&mut self, client: &dyn HttpClient, ui: &DownloadBar) -> Result<(), String> {
         self.inner.log_debug(ui, "processing episode");
         self.rename()?;
         self.make_symlink(ui)?;
-        self.normalize_id3v2(ui).await;
+        let transcript = self.inner.download_transcript(client, ui).await;
+        let chapters = self.fetch_chapters(client, ui).await;
+        self.normalize_id3v2(client, chapters, transcript, ui).await;
 
         Ok(())
     }
 
     fn rename(&mut self) -> Result<(), String> {
-        let new_name = &self.inner.config.name_pattern;
-        let mut new_name = sanitize_filename::sanitize(new_name);
-
-        let new_path = match self.path.extension() {
-            Some(extension) => {
-                let max_file_len: usize = 255;
-                let ext_len = extension.len() + 1; // + 1 for the dot.
-                let overflow = (new_name.len() + ext_len).saturating_sub(max_file_len);
-                for _ in 0..overflow {
-                    new_name.pop();
-                }
-
-                let mut new_path = self.path.with_file_name(new_name);
-                new_path.set_extension(extension);
-                new_path
-            }
-            None => self.path.with_file_name(new_name),
-        };
+        let extension = self.path.extension().and_then(|ext| ext.to_str());
+        let new_path = self.inner.named_path(extension);
 
         fs::rename(&self.path, &new_path).map_err(|_| "failed to rename episode".to_string())?;
         self.path = new_path;
@@ -501,9 +846,3 @@ impl AsRef<Episode> for DownloadedEpisode<'_> {
         &self.inner
     }
 }
-
-impl AsRef<Attributes> for Episode {
-    fn as_ref(&self) -> &Attributes {
-        &self.attrs
-    }
-}
@@ -0,0 +1,80 @@
+use serde::Serialize;
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// Everything that can make talecast fail, each with its own exit code.
+#[derive(Debug)]
+pub enum Error {
+    /// A config file couldn't be read or parsed, or one of its settings is invalid.
+    Config(String),
+    /// A feed or an episode couldn't be downloaded, or what was fetched isn't a valid feed.
+    Network(String),
+    /// A file or directory couldn't be read or written.
+    Io { path: PathBuf, source: io::Error },
+    /// Arguments that can't be used together, or invalid input like an OPML file without urls.
+    Usage(String),
+    /// The sync finished, but some of the podcasts or episodes failed.
+    Partial { podcasts: usize, episodes: usize },
+}
+
+impl Error {
+    /// For use with `map_err`, like `fs::read(&path).map_err(Error::io(&path))`.
+    pub fn io(path: impl AsRef<Path>) -> impl FnOnce(io::Error) -> Self {
+        let path = path.as_ref().to_path_buf();
+        move |source| Self::Io { path, source }
+    }
+
+    /// The exit code of the process when it fails with this error.
+    ///
+    /// | Code | Meaning                                                        |
+    /// | ---- | -------------------------------------------------------------- |
+    /// | 1    | A file or directory couldn't be read or written                |
+    /// | 2    | Invalid arguments, same as the ones rejected by the arg parser |
+    /// | 3    | Invalid config                                                 |
+    /// | 4    | None of the podcasts could be fetched                          |
+    /// | 5    | Some of the podcasts or episodes failed to sync                |
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::Io { .. } => 1,
+            Self::Usage(_) => 2,
+            Self::Config(_) => 3,
+            Self::Network(_) => 4,
+            Self::Partial { .. } => 5,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Config(msg) | Self::Network(msg) | Self::Usage(msg) => write!(f, "{}", msg),
+            Self::Io { path, source } => write!(f, "failed to access {:?}: {}", path, source),
+            Self::Partial { podcasts, episodes } => write!(
+                f,
+                "{} podcasts and {} episodes failed to sync",
+                podcasts, episodes
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Serialized as its message, for the JSON records of a sync.
+impl Serialize for Error {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
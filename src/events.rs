@@ -0,0 +1,164 @@
+use crate::display::ProgressBackend;
+use crate::episode::Episode;
+use serde::Serialize;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time;
+
+/// How often the progress of a download is reported at most.
+const PROGRESS_INTERVAL: time::Duration = time::Duration::from_millis(250);
+
+/// Where the progress events of every podcast are written to, one JSON object per line.
+#[derive(Clone)]
+pub struct EventSink(Arc<Mutex<Box<dyn Write + Send>>>);
+
+impl EventSink {
+    /// Writes to stderr, so the events don't mix with what's printed to stdout.
+    pub fn stderr() -> Self {
+        Self(Arc::new(Mutex::new(Box::new(std::io::stderr()))))
+    }
+
+    /// Connects to a unix socket that the events are written to.
+    #[cfg(unix)]
+    pub fn connect(path: &Path) -> std::io::Result<Self> {
+        let stream = std::os::unix::net::UnixStream::connect(path)?;
+        Ok(Self(Arc::new(Mutex::new(Box::new(stream)))))
+    }
+
+    #[cfg(not(unix))]
+    pub fn connect(_path: &Path) -> std::io::Result<Self> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "sockets are only supported on unix",
+        ))
+    }
+
+    fn emit(&self, event: &Event) {
+        let mut line = serde_json::to_string(event).expect("failed to serialize event");
+        line.push('\n');
+
+        // A wrapper that stopped listening shouldn't stop the sync.
+        let mut writer = self.0.lock().unwrap();
+        if writer.write_all(line.as_bytes()).is_ok() {
+            let _ = writer.flush();
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Event<'a> {
+    podcast: &'a str,
+    #[serde(flatten)]
+    kind: EventKind<'a>,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum EventKind<'a> {
+    Waiting,
+    Fetching,
+    BeginDownload {
+        title: &'a str,
+        guid: &'a str,
+        /// One-indexed.
+        index: usize,
+        total: usize,
+    },
+    BeginParallelDownload {
+        total: usize,
+    },
+    Progress {
+        downloaded: u64,
+        total: u64,
+    },
+    HookStatus,
+    Error {
+        error: &'a str,
+    },
+    Complete {
+        errors: usize,
+    },
+}
+
+/// Reports the progress of a podcast as JSON events.
+pub struct JsonEvents {
+    podcast_name: String,
+    sink: EventSink,
+    last_progress: Mutex<Option<time::Instant>>,
+}
+
+impl JsonEvents {
+    pub fn new(podcast_name: String, sink: EventSink) -> Self {
+        Self {
+            podcast_name,
+            sink,
+            last_progress: Mutex::new(None),
+        }
+    }
+
+    fn emit(&self, kind: EventKind) {
+        let event = Event {
+            podcast: &self.podcast_name,
+            kind,
+        };
+        self.sink.emit(&event);
+    }
+}
+
+impl ProgressBackend for JsonEvents {
+    fn waiting(&self) {
+        self.emit(EventKind::Waiting);
+    }
+
+    fn fetching(&self) {
+        self.emit(EventKind::Fetching);
+    }
+
+    fn init(&self) {}
+
+    fn begin_download(&self, episode: &Episode, index: usize, episode_qty: usize) {
+        self.emit(EventKind::BeginDownload {
+            title: episode.attrs.title(),
+            guid: episode.attrs.guid(),
+            index: index + 1,
+            total: episode_qty,
+        });
+    }
+
+    fn begin_parallel_download(&self, episode_qty: usize) {
+        self.emit(EventKind::BeginParallelDownload { total: episode_qty });
+    }
+
+    /// Skips updates that come in too fast, except for the one that finishes a download.
+    fn progress(&self, position: u64, length: u64) {
+        {
+            let mut last = self.last_progress.lock().unwrap();
+            let now = time::Instant::now();
+            let is_due = last.is_none_or(|last| now - last >= PROGRESS_INTERVAL);
+            let is_finished = length > 0 && position >= length;
+            if !is_due && !is_finished {
+                return;
+            }
+            *last = Some(now);
+        }
+
+        self.emit(EventKind::Progress {
+            downloaded: position,
+            total: length,
+        });
+    }
+
+    fn hook_status(&self) {
+        self.emit(EventKind::HookStatus);
+    }
+
+    fn error(&self, msg: &str) {
+        self.emit(EventKind::Error { error: msg });
+    }
+
+    fn complete(&self, error_qty: usize) {
+        self.emit(EventKind::Complete { errors: error_qty });
+    }
+}
@@ -0,0 +1,231 @@
+use crate::config::GlobalConfig;
+use crate::error::Error;
+use futures::future::BoxFuture;
+use futures::stream::BoxStream;
+use futures::{Stream, StreamExt};
+use std::fmt;
+
+pub use bytes::Bytes;
+pub use reqwest::header::{self, HeaderMap, HeaderName, HeaderValue};
+pub use reqwest::{StatusCode, Url};
+
+const MAX_REDIRECTS: usize = 10;
+
+/// A GET request.
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub url: Url,
+    pub headers: HeaderMap,
+}
+
+impl Request {
+    pub fn get(url: &str) -> Result<Self, HttpError> {
+        let url = Url::parse(url)
+            .map_err(|e| HttpError::InvalidRequest(format!("invalid url \"{}\": {}", url, e)))?;
+
+        Ok(Self {
+            url,
+            headers: HeaderMap::new(),
+        })
+    }
+
+    /// Sets a header, values that aren't valid in a header are left out.
+    pub fn header(mut self, name: HeaderName, value: &str) -> Self {
+        if let Ok(value) = HeaderValue::from_str(value) {
+            self.headers.insert(name, value);
+        }
+        self
+    }
+}
+
+/// The response to a [`Request`], with a body that's streamed in chunks.
+pub struct Response {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    body: BoxStream<'static, Result<Bytes, HttpError>>,
+}
+
+impl Response {
+    pub fn new(
+        status: StatusCode,
+        headers: HeaderMap,
+        body: impl Stream<Item = Result<Bytes, HttpError>> + Send + 'static,
+    ) -> Self {
+        Self {
+            status,
+            headers,
+            body: body.boxed(),
+        }
+    }
+
+    /// The size of the body according to the `Content-Length` header.
+    pub fn content_length(&self) -> Option<u64> {
+        self.headers
+            .get(header::CONTENT_LENGTH)
+            .and_then(|len| len.to_str().ok())
+            .and_then(|len| len.parse().ok())
+    }
+
+    pub fn bytes_stream(self) -> BoxStream<'static, Result<Bytes, HttpError>> {
+        self.body
+    }
+
+    pub async fn bytes(self) -> Result<Vec<u8>, HttpError> {
+        let mut buffer = vec![];
+        let mut stream = self.body;
+        while let Some(chunk) = stream.next().await {
+            buffer.extend(&chunk?);
+        }
+        Ok(buffer)
+    }
+
+    /// The body as text, with invalid UTF-8 replaced.
+    pub async fn text(self) -> Result<String, HttpError> {
+        let bytes = self.bytes().await?;
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+}
+
+impl fmt::Debug for Response {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Response")
+            .field("status", &self.status)
+            .field("headers", &self.headers)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Why a request failed before the server responded, or while reading the body.
+#[derive(Debug, Clone)]
+pub enum HttpError {
+    /// The request can't be sent as is, like with an invalid url or a broken redirect.
+    InvalidRequest(String),
+    /// Connecting or reading the response failed, which might work if we try again.
+    Connection(String),
+}
+
+impl fmt::Display for HttpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidRequest(msg) | Self::Connection(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for HttpError {}
+
+impl From<reqwest::Error> for HttpError {
+    fn from(e: reqwest::Error) -> Self {
+        let msg = match &e {
+            e if e.is_builder() => "invalid url",
+            e if e.is_connect() => "failed to connect to url",
+            e if e.is_timeout() => "request timed out",
+            e if e.is_status() => "server error",
+            e if e.is_redirect() => "too many redirects while connecting",
+            e if e.is_decode() => "failed to decode response",
+            e if e.is_body() => "failed to load chunk",
+            _ => "unexpected connection error",
+        };
+
+        if e.is_builder() {
+            Self::InvalidRequest(msg.to_string())
+        } else {
+            Self::Connection(msg.to_string())
+        }
+    }
+}
+
+/// What feeds, episodes and everything else are downloaded with.
+///
+/// Implementations send a single request and must not follow redirects, TaleCast follows
+/// them itself.
+pub trait HttpClient: Send + Sync {
+    fn send(&self, request: Request) -> BoxFuture<'_, Result<Response, HttpError>>;
+}
+
+impl fmt::Debug for dyn HttpClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("HttpClient")
+    }
+}
+
+/// Downloads the url, following its redirects.
+pub(crate) async fn get(client: &dyn HttpClient, url: &str) -> Result<Response, HttpError> {
+    follow_redirects(client, Request::get(url)?, |_, _| {}).await
+}
+
+/// Sends the request and follows its redirects, calling `on_redirect` with the status
+/// and location of each of them.
+///
+/// `304 Not Modified` isn't a redirect, it's returned like any other response.
+pub(crate) async fn follow_redirects(
+    client: &dyn HttpClient,
+    mut request: Request,
+    mut on_redirect: impl FnMut(StatusCode, &Url),
+) -> Result<Response, HttpError> {
+    let mut redirects = 0;
+
+    loop {
+        let response = client.send(request.clone()).await?;
+
+        let status = response.status;
+        if !status.is_redirection() || status == StatusCode::NOT_MODIFIED {
+            return Ok(response);
+        }
+
+        redirects += 1;
+        if redirects > MAX_REDIRECTS {
+            return Err(HttpError::InvalidRequest("too many redirects".into()));
+        }
+
+        let Some(location) = response
+            .headers
+            .get(header::LOCATION)
+            .and_then(|location| location.to_str().ok())
+            .and_then(|location| request.url.join(location).ok())
+        else {
+            return Err(HttpError::InvalidRequest(
+                "redirect without a valid location header".into(),
+            ));
+        };
+
+        on_redirect(status, &location);
+        request.url = location;
+    }
+}
+
+/// The [`HttpClient`] used outside of tests, sending the user agent from the config.
+#[derive(Debug, Clone)]
+pub struct ReqwestClient(reqwest::Client);
+
+impl ReqwestClient {
+    pub fn new(global_config: &GlobalConfig) -> Result<Self, Error> {
+        reqwest::Client::builder()
+            .user_agent(global_config.user_agent())
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .map(Self)
+            .map_err(|e| Error::Config(format!("failed to instantiate reqwest client: {}", e)))
+    }
+}
+
+impl HttpClient for ReqwestClient {
+    fn send(&self, request: Request) -> BoxFuture<'_, Result<Response, HttpError>> {
+        Box::pin(async move {
+            let response = self
+                .0
+                .get(request.url)
+                .headers(request.headers)
+                .send()
+                .await?;
+
+            let status = response.status();
+            let headers = response.headers().clone();
+            let body = response
+                .bytes_stream()
+                .map(|chunk| chunk.map_err(HttpError::from));
+
+            Ok(Response::new(status, headers, body))
+        })
+    }
+}
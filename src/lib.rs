@@ -0,0 +1,89 @@
+//! TaleCast is a podcast manager, this is the library that the `talecast` CLI is built on.
+//!
+//! The configs are loaded with [`GlobalConfig::load`] and [`PodcastConfigs::load`], from the
+//! same files the CLI uses. A podcast is fetched with [`Podcast::fetch`], which resolves its
+//! feed into [`Episode`]s with their file names and download paths evaluated. The episodes
+//! that a sync would download are found with [`Podcast::pending_episodes`], a single
+//! episode is downloaded with [`Podcast::download`], and all of them with
+//! [`Podcast::download_pending`]. Other patterns are evaluated for an episode with
+//! [`Podcast::evaluate`]. Everything is downloaded through an [`HttpClient`], a podcast is
+//! fetched with one of your own with [`Podcast::fetch_with_client`].
+//!
+//! Progress is reported to a [`ProgressBackend`], whose methods all default to doing nothing:
+//!
+//! ```no_run
+//! use talecast::{Error, FetchMode, GlobalConfig, Podcast, PodcastConfigs, ProgressBackend};
+//!
+//! struct PrintProgress;
+//!
+//! impl ProgressBackend for PrintProgress {
+//!     fn progress(&self, position: u64, length: u64) {
+//!         println!("{}/{} bytes", position, length);
+//!     }
+//! }
+//!
+//! async fn download_pending() -> Result<(), Error> {
+//!     let global_config = GlobalConfig::load()?;
+//!
+//!     for (name, config) in PodcastConfigs::load()? {
+//!         let mode = FetchMode::Conditional;
+//!         let podcast =
+//!             Podcast::fetch(&name, config, &global_config, mode, Box::new(PrintProgress)).await?;
+//!
+//!         for episode in podcast.pending_episodes() {
+//!             let record = podcast.download(episode, Box::new(PrintProgress)).await?;
+//!             println!("{}", record.to_json());
+//!         }
+//!     }
+//!
+//!     Ok(())
+//! }
+//! ```
+
+pub mod http;
+
+mod cache;
+mod chapters;
+mod check;
+mod config;
+mod display;
+mod download_tracker;
+mod episode;
+mod error;
+mod events;
+mod limits;
+mod opml;
+mod patterns;
+mod podcast;
+mod report;
+mod retry;
+mod tags;
+mod throttle;
+mod toml_merge;
+mod transcript;
+mod utils;
+
+pub use crate::config::{GlobalConfig, PodcastConfig, PodcastConfigs};
+pub use crate::display::ProgressBackend;
+pub use crate::download_tracker::DownloadedEpisodes;
+pub use crate::episode::Episode;
+pub use crate::error::Error;
+pub use crate::http::HttpClient;
+pub use crate::patterns::FullPattern;
+pub use crate::podcast::Podcast;
+pub use crate::report::{EpisodeListing, Record};
+pub use crate::utils::FetchMode;
+
+// Only meant for the CLI.
+#[doc(hidden)]
+pub mod cli {
+    pub use crate::check::run as check;
+    pub use crate::config::LogConfig;
+    pub use crate::display::ProgressFormat;
+    pub use crate::opml::{export, import};
+    pub use crate::podcast::EpisodeSelection;
+    pub use crate::report::{episode_table, sync_status, OutputFormat};
+    pub use crate::utils::{create_dir, edit_file, get_input, search_podcasts};
+}
+
+pub const APPNAME: &str = "talecast";
@@ -0,0 +1,60 @@
+use crate::throttle::RateLimiter;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::Mutex;
+use tokio::sync::OwnedSemaphorePermit;
+use tokio::sync::Semaphore;
+
+/// Limits how many podcasts are synced at once, how many connections are
+/// made to the same host at once, and the combined rate of all downloads.
+///
+/// A missing limit means unlimited, in which case no permit is handed out.
+#[derive(Debug, Default)]
+pub struct ConnectionLimits {
+    podcasts: Option<Arc<Semaphore>>,
+    max_per_host: Option<usize>,
+    hosts: Mutex<HashMap<String, Arc<Semaphore>>>,
+    bandwidth: Option<Arc<RateLimiter>>,
+}
+
+impl ConnectionLimits {
+    pub fn new(
+        max_podcasts: Option<usize>,
+        max_per_host: Option<usize>,
+        bandwidth: Option<RateLimiter>,
+    ) -> Self {
+        Self {
+            podcasts: max_podcasts.map(|max| Arc::new(Semaphore::new(max.max(1)))),
+            max_per_host: max_per_host.map(|max| max.max(1)),
+            hosts: Default::default(),
+            bandwidth: bandwidth.map(Arc::new),
+        }
+    }
+
+    /// The rate limiter shared by all downloads.
+    pub fn bandwidth(&self) -> Option<Arc<RateLimiter>> {
+        self.bandwidth.clone()
+    }
+
+    /// Waits until another podcast is allowed to sync. The podcast syncs until the permit is dropped.
+    pub async fn podcast_permit(&self) -> Option<OwnedSemaphorePermit> {
+        let semaphore = Arc::clone(self.podcasts.as_ref()?);
+        semaphore.acquire_owned().await.ok()
+    }
+
+    /// Waits until another connection to the host of the url is allowed.
+    pub async fn host_permit(&self, url: &str) -> Option<OwnedSemaphorePermit> {
+        let max = self.max_per_host?;
+        let host = reqwest::Url::parse(url).ok()?.host_str()?.to_string();
+
+        let semaphore = {
+            let mut hosts = self.hosts.lock().unwrap();
+            let semaphore = hosts
+                .entry(host)
+                .or_insert_with(|| Arc::new(Semaphore::new(max)));
+            Arc::clone(semaphore)
+        };
+
+        semaphore.acquire_owned().await.ok()
+    }
+}
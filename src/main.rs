@@ -1,21 +1,14 @@
-use crate::config::GlobalConfig;
-use crate::config::PodcastConfigs;
 use clap::Parser;
 use regex::Regex;
 use std::path::PathBuf;
-
-mod cache;
-mod config;
-mod display;
-mod download_tracker;
-mod episode;
-mod opml;
-mod patterns;
-mod podcast;
-mod tags;
-mod utils;
-
-pub const APPNAME: &'static str = "talecast";
+use talecast::cli;
+use talecast::cli::{EpisodeSelection, OutputFormat, ProgressFormat};
+use talecast::Error;
+use talecast::FetchMode;
+use talecast::GlobalConfig;
+use talecast::PodcastConfig;
+use talecast::PodcastConfigs;
+use talecast::Record;
 
 #[derive(Parser)]
 #[command(
@@ -39,7 +32,12 @@ struct Args {
         help = "Export your podcasts to an OPML file"
     )]
     export: Option<PathBuf>,
-    #[arg(short, long, help = "Print the downloaded paths to stdout")]
+    #[arg(
+        short,
+        long,
+        conflicts_with = "dry_run",
+        help = "Print the downloaded paths to stdout"
+    )]
     print: bool,
     #[arg(
         short,
@@ -61,69 +59,220 @@ struct Args {
         help = "Override the path to the config file"
     )]
     config: Option<PathBuf>,
+    #[arg(long, value_name = "NAME", help = "Remove a podcast")]
+    remove: Option<String>,
+    #[arg(
+        long,
+        requires = "remove",
+        help = "Delete the downloaded episodes and the download tracker of the removed podcast"
+    )]
+    delete_files: bool,
+    #[arg(long, num_args = 2, value_names = &["OLD", "NEW"], help = "Rename a podcast")]
+    rename: Vec<String>,
+    #[arg(
+        long,
+        requires = "rename",
+        help = "Move the downloaded episodes and the download tracker of the renamed podcast"
+    )]
+    move_files: bool,
     #[arg(long, help = "Edit the config.toml file")]
     edit_config: bool,
     #[arg(long, help = "Edit the podcasts.toml file")]
     edit_podcasts: bool,
+    #[arg(
+        long,
+        help = "Check the config files for invalid settings without connecting to the network"
+    )]
+    check: bool,
     #[arg(short, long, value_name = "QUERY",  num_args = 1.., help = "Search for podcasts to add")]
     search: Option<Vec<String>>,
     #[arg(long, help = "Print your podcasts to stdout")]
     list: bool,
+    #[arg(
+        long,
+        help = "Print the episodes of your podcasts to stdout. Can be combined with filter and output"
+    )]
+    episodes: bool,
+    #[arg(
+        long,
+        value_name = "GUID",
+        num_args = 1..,
+        help = "Download the episodes with these guids, regardless of max_episodes and similar settings"
+    )]
+    guid: Vec<String>,
+    #[arg(
+        long,
+        value_name = "INDEX",
+        num_args = 1..,
+        help = "Download the episodes at these indices as shown by --episodes, regardless of max_episodes and similar settings"
+    )]
+    index: Vec<usize>,
+    #[arg(
+        long,
+        value_name = "REGEX",
+        num_args = 1..,
+        help = "Download the episodes with titles matching these regex patterns, regardless of max_episodes and similar settings"
+    )]
+    title: Vec<String>,
+    #[arg(
+        long,
+        help = "Download the episodes selected with guid, index, or title even if they were downloaded before"
+    )]
+    force: bool,
+    #[arg(
+        long,
+        help = "Download the podcast feeds even if they haven't changed since the last sync"
+    )]
+    force_refresh: bool,
+    #[arg(
+        long,
+        conflicts_with = "force_refresh",
+        help = "Use the cached podcast feeds instead of connecting to the network"
+    )]
+    offline: bool,
+    #[arg(
+        long,
+        help = "Print what a sync would download without downloading anything"
+    )]
+    dry_run: bool,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = OutputFormat::Text,
+        conflicts_with = "dry_run",
+        help = "Print the results of a sync to stdout as text or as one JSON object per line"
+    )]
+    output: OutputFormat,
+    #[arg(
+        long,
+        value_enum,
+        help = "Show the progress of a sync as bars or as JSON events on stderr"
+    )]
+    progress: Option<ProgressFormat>,
+}
+
+/// Compiles a regex from the command line, case insensitive.
+fn parse_regex(arg: &str) -> Result<Regex, Error> {
+    Regex::new(&format!("(?i){}", arg))
+        .map_err(|e| Error::Usage(format!("invalid regex \"{}\": {}", arg, e)))
 }
 
-impl From<Args> for Action {
-    fn from(args: Args) -> Self {
-        let filter = args.filter.map(|filter| {
-            let filter = format!("(?i){}", filter); // Case insensitive
-            Regex::new(&filter).unwrap()
-        });
+impl TryFrom<Args> for Action {
+    type Error = Error;
+
+    fn try_from(args: Args) -> Result<Self, Error> {
+        let filter = args.filter.as_deref().map(parse_regex).transpose()?;
 
         let print = args.print;
         let catch_up = args.catch_up;
+        let fetch_mode = if args.offline {
+            FetchMode::Offline
+        } else if args.force_refresh {
+            FetchMode::ForceRefresh
+        } else {
+            FetchMode::Conditional
+        };
 
         if args.list {
-            return Self::List { filter };
+            return Ok(Self::List { filter });
+        }
+
+        if args.episodes {
+            return Ok(Self::Episodes {
+                filter,
+                fetch_mode,
+                output: args.output,
+            });
         }
 
         if args.edit_config {
             let path = GlobalConfig::default_path();
-            return Self::Edit { path };
+            return Ok(Self::Edit { path });
         }
 
         if args.edit_podcasts {
-            let path = config::PodcastConfigs::path();
-            return Self::Edit { path };
+            let path = PodcastConfigs::path();
+            return Ok(Self::Edit { path });
         }
 
         if let Some(query) = args.search {
             let query = query.join(" ");
-            return Self::Search { query, catch_up };
+            return Ok(Self::Search {
+                query,
+                catch_up,
+                fetch_mode,
+            });
+        }
+
+        if let Some(name) = args.remove {
+            return Ok(Self::Remove {
+                name,
+                delete_files: args.delete_files,
+                fetch_mode,
+            });
+        }
+
+        if let [old_name, new_name] = args.rename.as_slice() {
+            return Ok(Self::Rename {
+                old_name: old_name.clone(),
+                new_name: new_name.clone(),
+                move_files: args.move_files,
+                fetch_mode,
+            });
         }
 
         if let Some(path) = args.import {
-            return Self::Import { path, catch_up };
+            return Ok(Self::Import { path, catch_up });
         }
 
         if let Some(path) = args.export {
-            return Self::Export { path, filter };
+            return Ok(Self::Export { path, filter });
         }
 
         if !args.add.is_empty() {
             let url = args.add[0].to_string();
             let name = args.add.get(1).cloned();
 
-            return Self::Add {
+            return Ok(Self::Add {
                 url,
                 name,
                 catch_up,
-            };
+            });
         }
 
         if catch_up {
-            return Self::CatchUp { filter };
+            return Ok(Self::CatchUp { filter });
         }
 
-        Self::Sync { filter, print }
+        let selection = EpisodeSelection {
+            guids: args.guid,
+            indices: args.index,
+            titles: args
+                .title
+                .iter()
+                .map(String::as_str)
+                .map(parse_regex)
+                .collect::<Result<_, _>>()?,
+            force: args.force,
+        };
+
+        if !selection.is_empty() {
+            return Ok(Self::Download {
+                filter,
+                selection,
+                print,
+                fetch_mode,
+                output: args.output,
+            });
+        }
+
+        Ok(Self::Sync {
+            filter,
+            print,
+            fetch_mode,
+            dry_run: args.dry_run,
+            output: args.output,
+        })
     }
 }
 
@@ -131,6 +280,11 @@ enum Action {
     List {
         filter: Option<Regex>,
     },
+    Episodes {
+        filter: Option<Regex>,
+        fetch_mode: FetchMode,
+        output: OutputFormat,
+    },
     CatchUp {
         filter: Option<Regex>,
     },
@@ -150,20 +304,42 @@ enum Action {
         name: Option<String>,
         catch_up: bool,
     },
+    Remove {
+        name: String,
+        delete_files: bool,
+        fetch_mode: FetchMode,
+    },
+    Rename {
+        old_name: String,
+        new_name: String,
+        move_files: bool,
+        fetch_mode: FetchMode,
+    },
     Search {
         query: String,
         catch_up: bool,
+        fetch_mode: FetchMode,
+    },
+    Download {
+        filter: Option<Regex>,
+        selection: EpisodeSelection,
+        print: bool,
+        fetch_mode: FetchMode,
+        output: OutputFormat,
     },
     Sync {
         filter: Option<Regex>,
         print: bool,
+        fetch_mode: FetchMode,
+        dry_run: bool,
+        output: OutputFormat,
     },
 }
 
 use chrono::Local;
 use fern::Dispatch;
 
-fn setup_logging(config: &config::LogConfig) -> Result<PathBuf, fern::InitError> {
+fn setup_logging(config: &cli::LogConfig) -> Result<PathBuf, Error> {
     let base_config = Dispatch::new()
         .format(|out, message, record| {
             out.finish(format_args!(
@@ -176,12 +352,14 @@ fn setup_logging(config: &config::LogConfig) -> Result<PathBuf, fern::InitError>
         .level(config.level());
 
     let log_dir = PathBuf::from("/tmp/talecast");
-    utils::create_dir(&log_dir);
+    cli::create_dir(&log_dir)?;
     let log_path = log_dir.join(chrono::Local::now().format("%Y-%m-%d_%H-%M-%S").to_string());
 
-    let file_config = base_config.chain(fern::log_file(&log_path)?);
+    let file_config = base_config.chain(fern::log_file(&log_path).map_err(Error::io(&log_path))?);
 
-    file_config.apply()?;
+    file_config
+        .apply()
+        .expect("logging should only be set up once");
     Ok(log_path)
 }
 
@@ -189,31 +367,94 @@ fn setup_logging(config: &config::LogConfig) -> Result<PathBuf, fern::InitError>
 async fn main() {
     let args = Args::parse();
 
-    let global_config = match args.config.as_ref() {
-        Some(path) => GlobalConfig::load_from_path(path),
-        None => GlobalConfig::load(),
+    if let Err(e) = run(args).await {
+        eprintln!("error: {}", e);
+        std::process::exit(e.exit_code());
+    }
+}
+
+async fn run(args: Args) -> Result<(), Error> {
+    // Checked before loading, as loading fails on the first invalid setting.
+    if args.check {
+        return cli::check(args.config.as_deref());
+    }
+
+    let mut global_config = match args.config.as_ref() {
+        Some(path) => GlobalConfig::load_from_path(path)?,
+        None => GlobalConfig::load()?,
     };
 
-    let log_path = setup_logging(&global_config.log()).unwrap();
+    if let Some(progress) = args.progress {
+        global_config.set_progress(progress);
+    }
 
-    match Action::from(args) {
-        Action::Import { path, catch_up } => opml::import(&path, catch_up),
+    let log_path = setup_logging(&global_config.log())?;
 
-        Action::Edit { path } => utils::edit_file(&path),
+    match Action::try_from(args)? {
+        Action::Import { path, catch_up } => cli::import(&path, catch_up)?,
 
-        Action::CatchUp { filter } => config::PodcastConfigs::catch_up(filter),
+        Action::Edit { path } => cli::edit_file(&path)?,
+
+        Action::CatchUp { filter } => PodcastConfigs::catch_up(filter)?,
 
         Action::List { filter } => {
-            for (name, _) in config::PodcastConfigs::load().filter(filter) {
+            for (name, _) in PodcastConfigs::load()?.filter(filter) {
                 println!("{}", name);
             }
         }
 
-        Action::Search { query, catch_up } => {
-            utils::search_podcasts(&global_config, query, catch_up).await
+        Action::Episodes {
+            filter,
+            fetch_mode,
+            output,
+        } => {
+            let episodes = PodcastConfigs::load()?
+                .assert_not_empty()?
+                .filter(filter)
+                .episodes(global_config, fetch_mode)
+                .await?;
+
+            match output {
+                OutputFormat::Json => {
+                    for episode in episodes {
+                        println!("{}", episode.to_json());
+                    }
+                }
+                OutputFormat::Text => println!("{}", cli::episode_table(&episodes)),
+            }
+        }
+
+        Action::Remove {
+            name,
+            delete_files,
+            fetch_mode,
+        } => PodcastConfigs::remove(&name, delete_files, global_config, fetch_mode).await?,
+
+        Action::Rename {
+            old_name,
+            new_name,
+            move_files,
+            fetch_mode,
+        } => {
+            PodcastConfigs::rename(&old_name, &new_name, move_files, global_config, fetch_mode)
+                .await?
         }
 
-        Action::Export { path, filter } => opml::export(&path, filter).await,
+        Action::Search {
+            query,
+            catch_up,
+            fetch_mode,
+        } => {
+            if fetch_mode == FetchMode::Offline {
+                return Err(Error::Usage(
+                    "searching for podcasts is not possible in offline mode".into(),
+                ));
+            }
+
+            cli::search_podcasts(&global_config, query, catch_up).await?
+        }
+
+        Action::Export { path, filter } => cli::export(&path, filter).await?,
 
         Action::Add {
             name,
@@ -222,41 +463,125 @@ async fn main() {
         } => {
             let name = match name {
                 Some(name) => name,
-                None => match utils::get_input(Some("enter name of podcast: ")) {
+                None => match cli::get_input(Some("enter name of podcast: ")) {
                     Some(name) => name,
-                    None => return,
+                    None => return Ok(()),
                 },
             };
 
-            let podcast = config::PodcastConfig::new(url);
+            let podcast = PodcastConfig::new(url);
 
-            if config::PodcastConfigs::push(name.clone(), podcast) {
+            if PodcastConfigs::push(name.clone(), podcast)? {
                 eprintln!("'{}' added!", name);
                 if catch_up {
                     // Matches only the added podcast.
-                    let filter = Regex::new(&format!("^{}$", &name)).unwrap();
-                    config::PodcastConfigs::catch_up(Some(filter));
+                    let filter = Regex::new(&format!("^{}$", regex::escape(&name)))
+                        .expect("escaped names are valid regexes");
+                    PodcastConfigs::catch_up(Some(filter))?;
                 }
             } else {
                 eprintln!("'{}' already exists!", name);
             }
         }
 
-        Action::Sync { filter, print } => {
-            let paths = PodcastConfigs::load()
-                .assert_not_empty()
+        Action::Download {
+            filter,
+            selection,
+            print,
+            fetch_mode,
+            output,
+        } => {
+            if fetch_mode == FetchMode::Offline {
+                return Err(Error::Usage(
+                    "downloading episodes is not possible in offline mode".into(),
+                ));
+            }
+
+            let podcasts = PodcastConfigs::load()?.assert_not_empty()?.filter(filter);
+            let podcast_qty = podcasts.len();
+
+            // Indices only make sense within a single podcast.
+            if !selection.indices.is_empty() && podcast_qty != 1 {
+                return Err(Error::Usage(format!(
+                    "--index needs --filter to match exactly one podcast, but it matches {}",
+                    podcast_qty
+                )));
+            }
+
+            let quiet = global_config.has_quiet_stderr();
+            let records = podcasts
+                .download(global_config, selection, fetch_mode)
+                .await?;
+
+            print_records(&records, output, print, quiet);
+            cli::sync_status(&records, podcast_qty)?;
+        }
+
+        Action::Sync {
+            filter,
+            print: _,
+            fetch_mode,
+            dry_run: true,
+            output: _,
+        } => {
+            let plans = PodcastConfigs::load()?
+                .assert_not_empty()?
                 .filter(filter)
-                .sync(global_config, &log_path)
-                .await;
+                .dry_run(global_config, fetch_mode)
+                .await?;
 
-            eprintln!("Syncing complete!");
-            eprintln!("{} episodes downloaded.", paths.len());
+            for plan in plans {
+                println!("{}", plan);
+            }
+        }
 
-            if print {
-                for path in paths {
-                    println!("{}", path.to_str().unwrap());
-                }
+        Action::Sync {
+            filter,
+            print,
+            fetch_mode,
+            dry_run: false,
+            output,
+        } => {
+            if fetch_mode == FetchMode::Offline {
+                return Err(Error::Usage(
+                    "downloading episodes is not possible in offline mode".into(),
+                ));
+            }
+
+            let podcasts = PodcastConfigs::load()?.assert_not_empty()?.filter(filter);
+            let podcast_qty = podcasts.len();
+            let quiet = global_config.has_quiet_stderr();
+            let records = podcasts.sync(global_config, &log_path, fetch_mode).await?;
+
+            if !quiet {
+                eprintln!("Syncing complete!");
+            }
+            print_records(&records, output, print, quiet);
+            cli::sync_status(&records, podcast_qty)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Prints the outcome of a sync, with a summary on stderr unless it's `quiet`.
+fn print_records(records: &[Record], output: OutputFormat, print: bool, quiet: bool) {
+    let paths: Vec<&PathBuf> = records.iter().filter_map(Record::path).collect();
+    if !quiet {
+        eprintln!("{} episodes downloaded.", paths.len());
+    }
+
+    match output {
+        OutputFormat::Json => {
+            for record in records {
+                println!("{}", record.to_json());
+            }
+        }
+        OutputFormat::Text if print => {
+            for path in paths {
+                println!("{}", path.display());
             }
         }
+        OutputFormat::Text => {}
     }
 }
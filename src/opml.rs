@@ -1,5 +1,6 @@
 use crate::config;
 use crate::config::PodcastConfig;
+use crate::error::Error;
 use opml::OPML;
 use regex::Regex;
 use std::collections::HashMap;
@@ -7,27 +8,29 @@ use std::fs;
 use std::io::Write as IoWrite;
 use std::path::Path;
 
-pub async fn export(p: &Path, filter: Option<Regex>) {
-    let podcasts = config::PodcastConfigs::load()
-        .assert_not_empty()
+pub async fn export(p: &Path, filter: Option<Regex>) -> Result<(), Error> {
+    let podcasts = config::PodcastConfigs::load()?
+        .assert_not_empty()?
         .filter(filter);
 
     let opml = OPML::from(podcasts);
-    let xml_string = opml.to_string().unwrap();
+    let xml_string = opml
+        .to_string()
+        .map_err(|e| Error::Config(format!("failed to serialize OPML: {}", e)))?;
 
     fs::OpenOptions::new()
         .create(true)
         .write(true)
         .truncate(true)
         .open(p)
-        .unwrap()
-        .write_all(xml_string.as_bytes())
-        .unwrap();
+        .and_then(|mut file| file.write_all(xml_string.as_bytes()))
+        .map_err(Error::io(p))
 }
 
-pub fn import(p: &Path, catch_up: bool) {
-    let opml_string = std::fs::read_to_string(p).unwrap();
-    let opml = opml::OPML::from_str(&opml_string).unwrap();
+pub fn import(p: &Path, catch_up: bool) -> Result<(), Error> {
+    let opml_string = std::fs::read_to_string(p).map_err(Error::io(p))?;
+    let opml = opml::OPML::from_str(&opml_string)
+        .map_err(|e| Error::Usage(format!("invalid OPML file {:?}: {}", p, e)))?;
 
     let mut podcasts = HashMap::default();
 
@@ -44,22 +47,21 @@ pub fn import(p: &Path, catch_up: bool) {
 
         let (name, mut podcast) = match (title, podcast.xml_url) {
             (None, None) => {
-                eprintln!("importing failed due to feed with missing title and url");
-                std::process::exit(1);
+                return Err(Error::Usage(
+                    "importing failed due to feed with missing title and url".into(),
+                ));
             }
             (Some(title), None) => {
-                eprintln!(
+                return Err(Error::Usage(format!(
                     "importing failed due to following podcast missing its' url: {}",
                     title
-                );
-                std::process::exit(1);
+                )));
             }
             (None, Some(url)) => {
-                eprintln!(
+                return Err(Error::Usage(format!(
                     "importing failed due to podcast with following url missing a title: {}",
                     url
-                );
-                std::process::exit(1);
+                )));
             }
             (Some(title), Some(url)) => (title, PodcastConfig::new(url)),
         };
@@ -73,7 +75,8 @@ pub fn import(p: &Path, catch_up: bool) {
 
     if podcasts.is_empty() {
         eprintln!("no podcasts found.");
+        Ok(())
     } else {
-        config::PodcastConfigs::extend(podcasts);
+        config::PodcastConfigs::extend(podcasts)
     }
 }
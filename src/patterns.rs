@@ -1,6 +1,7 @@
 use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 
+use crate::transcript;
 use crate::utils;
 
 use regex::Regex;
@@ -9,66 +10,428 @@ use regex::Regex;
 pub struct FullPattern(Vec<Segment>);
 
 impl FullPattern {
-    pub fn from_str(s: &str) -> Self {
+    /// Parses the pattern, or describes why it's invalid.
+    pub fn parse(s: &str) -> Result<Self, String> {
         let mut segments: Vec<Segment> = vec![];
+        // The segments of the optional section we're in, if any.
+        let mut section: Option<Vec<Segment>> = None;
         let mut text = String::new();
         let mut pattern = String::new();
 
         let mut is_inside = false;
-
-        for c in s.chars() {
-            if c == '}' {
-                assert!(is_inside);
+        let mut quotes = Quotes::default();
+        let mut chars = s.chars().enumerate().peekable();
+
+        while let Some((index, c)) = chars.next() {
+            if is_inside && quotes.scan(c) {
+                pattern.push(c);
+            } else if !is_inside && c == '{' && matches!(chars.peek(), Some((_, '['))) {
+                if section.is_some() {
+                    return Err(format!("nested '{{[' at position {}: \"{}\"", index, s));
+                }
+                chars.next();
+                let text = std::mem::take(&mut text);
+                segments.push(Segment::Text(text));
+                section = Some(vec![]);
+            } else if !is_inside
+                && section.is_some()
+                && c == ']'
+                && matches!(chars.peek(), Some((_, '}')))
+            {
+                chars.next();
+                let mut optional = section.take().unwrap_or_default();
+                let text = std::mem::take(&mut text);
+                optional.push(Segment::Text(text));
+                segments.push(Segment::Optional(optional));
+            } else if c == '}' {
+                if !is_inside {
+                    return Err(format!("unmatched '}}' at position {}: \"{}\"", index, s));
+                }
                 let text_pattern = std::mem::take(&mut pattern);
-                let pattern = Pattern::from_str(&text_pattern);
+                let pattern = Substitution::parse(&text_pattern)?;
                 let segment = Segment::Pattern(pattern);
-                segments.push(segment);
+                section.as_mut().unwrap_or(&mut segments).push(segment);
                 is_inside = false;
             } else if c == '{' {
-                assert!(!is_inside);
+                if is_inside {
+                    return Err(format!("nested '{{' at position {}: \"{}\"", index, s));
+                }
                 let text = std::mem::take(&mut text);
-                segments.push(Segment::Text(text));
+                section
+                    .as_mut()
+                    .unwrap_or(&mut segments)
+                    .push(Segment::Text(text));
                 is_inside = true;
+            } else if is_inside {
+                pattern.push(c);
             } else {
-                if is_inside {
-                    pattern.push(c);
-                } else {
-                    text.push(c);
-                }
+                text.push(c);
             }
         }
 
-        assert!(!is_inside);
+        if quotes.open {
+            return Err(format!("unclosed '\"': \"{}\"", s));
+        }
+
+        if is_inside {
+            return Err(format!("unclosed '{{': \"{}\"", s));
+        }
+
+        if section.is_some() {
+            return Err(format!("unclosed '{{[': \"{}\"", s));
+        }
+
         if !text.is_empty() {
             segments.push(Segment::Text(text));
         }
 
-        Self(segments)
+        Ok(Self(segments))
     }
 
-    pub fn direct_eval(s: &str, data: EvalData<'_>) -> String {
-        Self::from_str(s).evaluate(data)
+    /// Evaluates the pattern like [`Evaluate::evaluate`], but fails if a value is missing
+    /// instead of inserting a placeholder.
+    pub(crate) fn evaluate_strict(&self, data: EvalData<'_>) -> Result<String, String> {
+        let mut output = String::new();
+
+        for segment in &self.0 {
+            let text = segment
+                .value(data)
+                .map_err(|missing| format!("no value found for {{{}}}", missing.source))?;
+            output.push_str(&text);
+        }
+
+        Ok(output)
     }
 
-    pub fn direct_eval_file(s: &str, data: EvalData<'_>) -> PathBuf {
-        let p = PathBuf::from(Self::direct_eval(s, data));
-        if let Some(path) = p.parent() {
-            utils::create_dir(&path);
+    /// Parses and evaluates the pattern, with [`FullPattern::evaluate_strict`] if `strict`.
+    pub(crate) fn direct_eval(
+        s: &str,
+        data: EvalData<'_>,
+        strict: bool,
+    ) -> Result<String, EvalError> {
+        let pattern = Self::parse(s).map_err(EvalError::Invalid)?;
+
+        if !strict {
+            return Ok(pattern.evaluate(data));
         }
-        p
+
+        pattern.evaluate_strict(data).map_err(|e| {
+            EvalError::Missing(format!(
+                "failed to evaluate \"{}\" for episode \"{}\": {}",
+                s,
+                data.episode.title(),
+                e
+            ))
+        })
     }
 
-    pub fn direct_eval_dir(s: &str, data: EvalData<'_>) -> PathBuf {
-        let p = PathBuf::from(Self::direct_eval(s, data));
-        utils::create_dir(&p);
-        p
+    pub(crate) fn direct_eval_path(
+        s: &str,
+        data: EvalData<'_>,
+        strict: bool,
+    ) -> Result<PathBuf, EvalError> {
+        Self::direct_eval(s, data, strict).map(PathBuf::from)
+    }
+}
+
+/// Why a pattern couldn't be evaluated for an episode.
+#[derive(Debug)]
+pub enum EvalError {
+    /// The pattern is invalid, so it can't be evaluated for any episode.
+    Invalid(String),
+    /// A value is missing for this episode, and `strict_patterns` is on.
+    Missing(String),
+}
+
+impl From<EvalError> for Error {
+    fn from(e: EvalError) -> Self {
+        match e {
+            EvalError::Invalid(msg) | EvalError::Missing(msg) => Error::Config(msg),
+        }
     }
 }
 
 #[derive(Clone, Debug)]
 enum Segment {
     Text(String),
-    Pattern(Pattern),
+    Pattern(Substitution),
+    /// A section in `{[` and `]}` that's left out if any of its patterns is missing.
+    Optional(Vec<Segment>),
+}
+
+impl Segment {
+    /// The text of the segment, or the pattern that's missing a value.
+    fn value(&self, data: EvalData) -> Result<String, &Substitution> {
+        match self {
+            Self::Text(text) => Ok(text.clone()),
+            Self::Pattern(pattern) => pattern.value(data).ok_or(pattern),
+            Self::Optional(segments) => Ok(segments
+                .iter()
+                .map(|segment| segment.value(data))
+                .collect::<Result<String, _>>()
+                .unwrap_or_default()),
+        }
+    }
+}
+
+/// Keeps track of whether we're inside a quoted filter argument, where a `\` can escape
+/// a quote.
+#[derive(Default)]
+struct Quotes {
+    open: bool,
+    escaped: bool,
+}
+
+impl Quotes {
+    /// Whether the character is part of a quoted argument, including its quotes.
+    fn scan(&mut self, c: char) -> bool {
+        if self.escaped {
+            self.escaped = false;
+            return true;
+        }
+
+        match c {
+            '"' => {
+                self.open = !self.open;
+                true
+            }
+            '\\' if self.open => {
+                self.escaped = true;
+                true
+            }
+            _ => self.open,
+        }
+    }
+}
+
+/// Splits the string on the separator, unless it's inside a quoted argument.
+fn split_unquoted(s: &str, separator: char) -> Vec<&str> {
+    let mut parts = vec![];
+    let mut quotes = Quotes::default();
+    let mut start = 0;
+
+    for (index, c) in s.char_indices() {
+        if !quotes.scan(c) && c == separator {
+            parts.push(&s[start..index]);
+            start = index + c.len_utf8();
+        }
+    }
+
+    parts.push(&s[start..]);
+    parts
+}
+
+/// Removes the quotes around an argument and resolves the `\"` and `\\` escapes in it.
+/// Unquoted arguments are taken as is.
+fn unquote(arg: &str) -> String {
+    let Some(inner) = arg.strip_prefix('"').and_then(|arg| arg.strip_suffix('"')) else {
+        return arg.to_string();
+    };
+
+    let mut unquoted = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some(escaped @ ('"' | '\\'))) => {
+                unquoted.push(escaped);
+                chars.next();
+            }
+            (c, _) => unquoted.push(c),
+        }
+    }
+    unquoted
+}
+
+/// What's inside the braces: a pattern with the filters its value goes through, like
+/// `{rss::episode::title | lower}`, and the patterns to fall back to if it's missing, like
+/// `{rss::episode::itunes:episode ?? guid}`.
+#[derive(Clone, Debug)]
+struct Substitution {
+    /// The text inside the braces.
+    source: String,
+    /// The first of these with a value is used.
+    patterns: Vec<Pattern>,
+    filters: Vec<Filter>,
+}
+
+impl Substitution {
+    fn parse(s: &str) -> Result<Self, String> {
+        let mut parts = split_unquoted(s, '|').into_iter();
+        let patterns: Vec<&str> = parts.next().unwrap_or_default().split("??").collect();
+        let filters = parts
+            .map(|filter| Filter::parse(filter.trim()))
+            .collect::<Result<Vec<_>, String>>()?;
+
+        // Spaces around the pipes and question marks are only there for readability.
+        let trim = patterns.len() > 1 || !filters.is_empty();
+        let patterns = patterns
+            .into_iter()
+            .map(|pattern| Pattern::parse(if trim { pattern.trim() } else { pattern }))
+            .collect::<Result<Vec<_>, String>>()?;
+
+        Ok(Self {
+            source: s.to_string(),
+            patterns,
+            filters,
+        })
+    }
+
+    fn value(&self, data: EvalData) -> Option<String> {
+        let value = self.patterns.iter().find_map(|pattern| pattern.value(data));
+
+        self.filters
+            .iter()
+            .fold(value, |value, filter| filter.apply(value))
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Filter {
+    Lower,
+    Upper,
+    Trim,
+    /// Keeps at most this many characters.
+    Truncate(usize),
+    /// Pads the start of the value with `fill` until it's `width` characters long.
+    Pad {
+        width: usize,
+        fill: char,
+    },
+    Replace {
+        from: String,
+        to: String,
+    },
+    RegexReplace {
+        regex: Regex,
+        replacement: String,
+    },
+    /// Lowercase alphanumeric words separated by dashes.
+    Slugify,
+    StripHtml,
+    /// Used if the value is missing or empty.
+    Default(String),
+}
+
+impl Filter {
+    const NAMES: [&'static str; 10] = [
+        "lower",
+        "upper",
+        "trim",
+        "truncate",
+        "pad",
+        "replace",
+        "regex_replace",
+        "slugify",
+        "strip_html",
+        "default",
+    ];
+
+    /// Parses a filter like `truncate:60` or `replace:" ":"_"`.
+    fn parse(s: &str) -> Result<Self, String> {
+        let mut parts = split_unquoted(s, ':')
+            .into_iter()
+            .map(|part| unquote(part.trim()));
+        let name = parts.next().unwrap_or_default();
+        let args: Vec<String> = parts.collect();
+
+        let number = |arg: &str| {
+            arg.parse::<usize>()
+                .map_err(|_| format!("expected a number in filter \"{}\", got \"{}\"", s, arg))
+        };
+
+        let filter = match (name.as_str(), args.as_slice()) {
+            ("lower", []) => Self::Lower,
+            ("upper", []) => Self::Upper,
+            ("trim", []) => Self::Trim,
+            ("truncate", [len]) => Self::Truncate(number(len)?),
+            ("pad", [width]) => Self::Pad {
+                width: number(width)?,
+                fill: '0',
+            },
+            ("pad", [width, fill]) => {
+                let mut chars = fill.chars();
+                let (Some(fill), None) = (chars.next(), chars.next()) else {
+                    return Err(format!(
+                        "expected a single character to pad with in filter \"{}\", got \"{}\"",
+                        s, fill
+                    ));
+                };
+
+                Self::Pad {
+                    width: number(width)?,
+                    fill,
+                }
+            }
+            ("replace", [from, to]) => Self::Replace {
+                from: from.clone(),
+                to: to.clone(),
+            },
+            ("regex_replace", [regex, replacement]) => Self::RegexReplace {
+                regex: Regex::new(regex)
+                    .map_err(|e| format!("invalid regex in filter \"{}\": {}", s, e))?,
+                replacement: replacement.clone(),
+            },
+            ("slugify", []) => Self::Slugify,
+            ("strip_html", []) => Self::StripHtml,
+            ("default", [default]) => Self::Default(default.clone()),
+            (name, _) if Self::NAMES.contains(&name) => {
+                return Err(format!("wrong number of arguments in filter: \"{}\"", s))
+            }
+            _ => return Err(format!("unknown filter: \"{}\"", s)),
+        };
+
+        Ok(filter)
+    }
+
+    fn apply(&self, value: Option<String>) -> Option<String> {
+        match self {
+            Self::Default(default) => match value {
+                Some(value) if !value.is_empty() => Some(value),
+                _ => Some(default.clone()),
+            },
+            _ => value.map(|value| self.transform(value)),
+        }
+    }
+
+    fn transform(&self, value: String) -> String {
+        match self {
+            Self::Lower => value.to_lowercase(),
+            Self::Upper => value.to_uppercase(),
+            Self::Trim => value.trim().to_string(),
+            Self::Truncate(len) => value.chars().take(*len).collect(),
+            Self::Pad { width, fill } => {
+                let padding = width.saturating_sub(value.chars().count());
+                std::iter::repeat_n(*fill, padding)
+                    .chain(value.chars())
+                    .collect()
+            }
+            Self::Replace { from, to } => value.replace(from.as_str(), to),
+            Self::RegexReplace { regex, replacement } => {
+                regex.replace_all(&value, replacement.as_str()).into_owned()
+            }
+            Self::Slugify => slugify(&value),
+            Self::StripHtml => transcript::strip_html(&value),
+            Self::Default(_) => value,
+        }
+    }
+}
+
+fn slugify(s: &str) -> String {
+    let mut slug = String::new();
+
+    for c in s.chars() {
+        if c.is_alphanumeric() {
+            slug.extend(c.to_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+
+    if slug.ends_with('-') {
+        slug.pop();
+    }
+
+    slug
 }
 
 #[derive(Debug, Clone)]
@@ -78,14 +441,21 @@ enum Pattern {
 }
 
 impl Pattern {
-    fn from_str(s: &str) -> Self {
+    fn parse(s: &str) -> Result<Self, String> {
         if let Some(unit) = UnitPattern::from_str(s) {
-            Self::Unit(unit)
+            Ok(Self::Unit(unit))
         } else if let Some(data) = DataPattern::from_str(s) {
-            Self::Data(data)
+            Ok(Self::Data(data))
         } else {
-            eprintln!("invalid pattern: \"{}\"", s);
-            std::process::exit(1);
+            Err(format!("invalid pattern: \"{}\"", s))
+        }
+    }
+
+    /// The value of the pattern, or `None` if it's missing.
+    fn value(&self, data: EvalData) -> Option<String> {
+        match self {
+            Self::Unit(pattern) => pattern.value(data),
+            Self::Data(pattern) => pattern.value(data),
         }
     }
 }
@@ -112,13 +482,12 @@ impl DataPattern {
     }
 }
 
-impl Evaluate for DataPattern {
-    fn evaluate(&self, data: EvalData) -> String {
+impl DataPattern {
+    fn value(&self, data: EvalData) -> Option<String> {
         use chrono::TimeZone;
         use DataPatternType as Ty;
-        let null = "<value not found>";
 
-        match self.ty {
+        let value = match self.ty {
             Ty::CurrDate => {
                 let now = utils::current_unix().as_secs() as i64;
                 let formatting = &self.data;
@@ -146,14 +515,16 @@ impl Evaluate for DataPattern {
             Ty::RssEpisode => {
                 let key = &self.data;
 
-                data.episode.get_str(&key).unwrap_or(null).to_string()
+                data.episode.get_text(key)?
             }
             Ty::RssChannel => {
                 let key = &self.data;
 
-                data.podcast.get_str(&key).unwrap_or(null).to_string()
+                data.podcast.get_text(key)?
             }
-        }
+        };
+
+        Some(value)
     }
 }
 
@@ -187,6 +558,12 @@ enum UnitPattern {
     PodName,
     AppName,
     Home,
+    Index,
+    Season,
+    Episode,
+    Duration,
+    EpisodeType,
+    Ext,
 }
 
 impl UnitPattern {
@@ -197,21 +574,36 @@ impl UnitPattern {
             "podname" => Self::PodName,
             "appname" => Self::AppName,
             "home" => Self::Home,
+            "index" => Self::Index,
+            "season" => Self::Season,
+            "episode" => Self::Episode,
+            "duration" => Self::Duration,
+            "episode_type" => Self::EpisodeType,
+            "ext" => Self::Ext,
             _ => return None,
         }
         .into()
     }
 }
 
-impl Evaluate for UnitPattern {
-    fn evaluate(&self, data: EvalData) -> String {
-        match self {
+impl UnitPattern {
+    fn value(&self, data: EvalData) -> Option<String> {
+        let value = match self {
             Self::Guid => data.episode.guid().to_string(),
             Self::Url => data.episode.url().to_string(),
             Self::PodName => data.pod_name.to_string(),
             Self::AppName => crate::APPNAME.to_string(),
-            Self::Home => home().unwrap_or("<missing home>".to_string()),
-        }
+            Self::Home => home()?,
+            // Counting from 1 like the episode listing does.
+            Self::Index => (data.index + 1).to_string(),
+            Self::Season => data.episode.season()?.to_string(),
+            Self::Episode => data.episode.episode_number()?.to_string(),
+            Self::Duration => utils::format_duration(data.episode.duration()?),
+            Self::EpisodeType => data.episode.episode_type()?,
+            Self::Ext => data.episode.extension()?,
+        };
+
+        Some(value)
     }
 }
 
@@ -221,6 +613,7 @@ fn home() -> Option<String> {
 use std::path::PathBuf;
 
 use crate::config::EvalData;
+use crate::error::Error;
 
 pub trait Evaluate {
     fn evaluate(&self, data: EvalData) -> String;
@@ -237,14 +630,209 @@ impl Evaluate for FullPattern {
         let mut output = String::new();
 
         for segment in &self.0 {
-            let text = match segment {
-                Segment::Text(text) => text.clone(),
-                Segment::Pattern(Pattern::Unit(pattern)) => pattern.evaluate(data),
-                Segment::Pattern(Pattern::Data(pattern)) => pattern.evaluate(data),
-            };
+            let text = segment
+                .value(data)
+                .unwrap_or_else(|_| "<value not found>".to_string());
             output.push_str(&text);
         }
 
         output
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::episode::{Attributes, RawEpisode};
+    use crate::podcast::RawPodcast;
+    use serde_json::json;
+
+    fn raw(value: serde_json::Value) -> serde_json::Map<String, serde_json::Value> {
+        value.as_object().unwrap().clone()
+    }
+
+    /// Evaluates the pattern for an episode like the one in a feed, or fails like strict
+    /// patterns do.
+    fn try_evaluate(pattern: &str, strict: bool) -> Result<String, String> {
+        let podcast = RawPodcast::new(raw(json!({"title": "Fixtures"})));
+        let episode = Attributes::new(RawEpisode::new(raw(json!({
+            "title": "Hello, World! Part 2",
+            "guid": "g1",
+            "pubDate": "01 Jan 2024 10:00:00 +0000",
+            "enclosure": {"@url": "http://fixtures.test/episode.m4a", "@type": "audio/mp4"},
+            "itunes:episode": 7,
+            "itunes:duration": "1:02:03",
+            "itunes:episodeType": "Full",
+            "description": "<p>Some <b>bold</b> text</p>",
+        }))))
+        .unwrap();
+
+        let data = EvalData::new("Fixture Pod", &podcast, &episode, 0);
+        let pattern = FullPattern::parse(pattern)?;
+
+        if strict {
+            pattern.evaluate_strict(data)
+        } else {
+            Ok(pattern.evaluate(data))
+        }
+    }
+
+    fn evaluate(pattern: &str) -> String {
+        try_evaluate(pattern, false).unwrap()
+    }
+
+    #[test]
+    fn filters_are_applied_in_order() {
+        let name = evaluate(r#"{rss::episode::title | lower | replace:" ":"_" | truncate:12}"#);
+
+        assert_eq!(name, "hello,_world");
+    }
+
+    #[test]
+    fn case_filters() {
+        let name = evaluate("{rss::episode::title | upper} {podname|lower}");
+
+        assert_eq!(name, "HELLO, WORLD! PART 2 fixture pod");
+    }
+
+    #[test]
+    fn pad_filter() {
+        let name = evaluate(
+            r#"{rss::episode::itunes:episode | pad:3} {rss::episode::itunes:episode | pad:3:"_"}"#,
+        );
+
+        assert_eq!(name, "007 __7");
+    }
+
+    #[test]
+    fn slugify_filter() {
+        assert_eq!(
+            evaluate("{rss::episode::title | slugify}"),
+            "hello-world-part-2"
+        );
+    }
+
+    #[test]
+    fn strip_html_filter() {
+        assert_eq!(
+            evaluate("{rss::episode::description | strip_html | trim}"),
+            "Some bold text"
+        );
+    }
+
+    #[test]
+    fn regex_replace_filter() {
+        let name = evaluate(r#"{rss::episode::title | regex_replace:"Part (\d+)":"pt$1"}"#);
+
+        assert_eq!(name, "Hello, World! pt2");
+    }
+
+    #[test]
+    fn default_filter() {
+        let name = evaluate(
+            r#"S{rss::episode::itunes:season | default:"0" | pad:2}E{rss::episode::itunes:episode | default:"0" | pad:2}"#,
+        );
+
+        assert_eq!(name, "S00E07");
+    }
+
+    #[test]
+    fn quoted_arguments_can_contain_syntax() {
+        let name = evaluate(r#"{rss::episode::title | replace:", ":" | " | replace:"!":"{\"}"}"#);
+
+        assert_eq!(name, r#"Hello | World{"} Part 2"#);
+    }
+
+    #[test]
+    fn fallbacks_use_first_value_found() {
+        let name = evaluate(
+            "{rss::episode::itunes:season ?? rss::episode::itunes:episode ?? guid}-\
+             {rss::episode::itunes:season??guid | upper}",
+        );
+
+        assert_eq!(name, "7-G1");
+    }
+
+    #[test]
+    fn optional_sections_need_all_values() {
+        let name = evaluate(
+            "{[S{rss::episode::itunes:season}E{rss::episode::itunes:episode} ]}\
+             {[E{rss::episode::itunes:episode | pad:2} ]}{guid}",
+        );
+
+        assert_eq!(name, "E07 g1");
+    }
+
+    #[test]
+    fn missing_season_can_be_left_out() {
+        assert_eq!(evaluate("{[S{season}]}E{episode}"), "E7");
+    }
+
+    #[test]
+    fn brackets_and_backslashes_are_text() {
+        // Patterns from before optional sections existed mean the same thing.
+        assert_eq!(
+            evaluate("[{podname}] {rss::episode::title} [{guid}]"),
+            "[Fixture Pod] Hello, World! Part 2 [g1]"
+        );
+        assert_eq!(evaluate(r"C:\pods\{guid}\[x]"), r"C:\pods\g1\[x]");
+        assert_eq!(evaluate("[{guid}] ]"), "[g1] ]");
+    }
+
+    #[test]
+    fn episode_unit_patterns() {
+        let name = evaluate("{index}|{episode | pad:3}|{duration}|{episode_type}|{ext}");
+
+        assert_eq!(name, "1|007|01:02:03|full|m4a");
+    }
+
+    #[test]
+    fn missing_values_get_placeholder() {
+        assert_eq!(
+            evaluate("{rss::episode::itunes:season}"),
+            "<value not found>"
+        );
+    }
+
+    #[test]
+    fn strict_patterns_fail_on_missing_values() {
+        let result = try_evaluate("{rss::episode::itunes:season}", true);
+
+        assert_eq!(
+            result,
+            Err("no value found for {rss::episode::itunes:season}".to_string())
+        );
+    }
+
+    #[test]
+    fn strict_patterns_allow_fallbacks_and_optional_sections() {
+        let name = try_evaluate(
+            "{[S{rss::episode::itunes:season} ]}{rss::episode::itunes:season ?? guid}",
+            true,
+        );
+
+        assert_eq!(name, Ok("g1".to_string()));
+    }
+
+    #[test]
+    fn invalid_sections_are_rejected() {
+        for pattern in ["{[a {[b]} c]}", "a]}", "{[{guid}", "{guid ?? nope}"] {
+            assert!(FullPattern::parse(pattern).is_err(), "{}", pattern);
+        }
+    }
+
+    #[test]
+    fn invalid_filters_are_rejected() {
+        for pattern in [
+            "{guid | nope}",
+            "{guid | truncate}",
+            "{guid | truncate:many}",
+            "{guid | lower:1}",
+            r#"{guid | pad:3:"ab"}"#,
+            r#"{guid | regex_replace:"(":"x"}"#,
+            r#"{guid | replace:"a:"b"}"#,
+        ] {
+            assert!(FullPattern::parse(pattern).is_err(), "{}", pattern);
+        }
+    }
+}
@@ -3,42 +3,216 @@ use crate::config::EvalData;
 use crate::config::PodcastConfig;
 use crate::config::{Config, GlobalConfig};
 use crate::display::DownloadBar;
+use crate::display::ProgressBackend;
 use crate::episode;
+use crate::episode::DownloadedEpisode;
 use crate::episode::Episode;
 use crate::episode::RawEpisode;
+use crate::error::Error;
+use crate::http::{HttpClient, ReqwestClient};
+use crate::limits::ConnectionLimits;
+use crate::patterns::{EvalError, Evaluate, FullPattern};
+use crate::report::EpisodeListing;
+use crate::report::Record;
 use crate::tags;
+use crate::throttle::Throttle;
 use crate::utils;
+use crate::utils::FetchMode;
+use futures::stream;
+use futures::StreamExt;
 use quickxml_to_serde::{xml_string_to_json, Config as XmlConfig};
+use regex::Regex;
 use serde_json::Map;
 use serde_json::Value;
 use std::path::PathBuf;
 use std::sync::Arc;
 
+/// Where the files of an episode go, see [`Podcast::paths_as`].
+#[derive(Debug, Clone)]
+pub struct EpisodePaths {
+    pub download_dir: PathBuf,
+    pub tracker: PathBuf,
+    pub symlink_dir: Option<PathBuf>,
+}
+
+/// Which episodes to download on demand, see `Podcast::download_selected`.
+#[derive(Debug, Default)]
+pub struct EpisodeSelection {
+    pub guids: Vec<String>,
+    /// As shown by `--episodes`, starting from 1 for the oldest episode.
+    pub indices: Vec<usize>,
+    pub titles: Vec<Regex>,
+    /// Download episodes again even if they're in the download tracker.
+    pub force: bool,
+}
+
+impl EpisodeSelection {
+    pub fn is_empty(&self) -> bool {
+        self.guids.is_empty() && self.indices.is_empty() && self.titles.is_empty()
+    }
+
+    fn matches(&self, episode: &Episode) -> bool {
+        self.guids.iter().any(|guid| guid == episode.attrs.guid())
+            || self.indices.contains(&(episode.index + 1))
+            || self
+                .titles
+                .iter()
+                .any(|title| title.is_match(episode.attrs.title()))
+    }
+}
+
 fn get_inner_channel(xml: String) -> Option<serde_json::Value> {
     let conf = XmlConfig::new_with_defaults();
-    std::mem::take(
-        xml_string_to_json(xml, &conf)
-            .ok()?
-            .get_mut("rss")?
-            .get_mut("channel")?,
-    )
-    .into()
+    let mut root = xml_string_to_json(xml, &conf).ok()?;
+
+    if let Some(channel) = root.get_mut("rss").and_then(|rss| rss.get_mut("channel")) {
+        return std::mem::take(channel).into();
+    }
+
+    let feed = std::mem::take(root.get_mut("feed")?);
+    atom_to_channel(feed)
+}
+
+/// Returns the values of a key that might either be a single object or an array of them.
+fn as_list(val: Option<&Value>) -> Vec<&Value> {
+    match val {
+        Some(Value::Array(vals)) => vals.iter().collect(),
+        Some(val) => vec![val],
+        None => vec![],
+    }
+}
+
+/// Finds the href of an atom `<link>` tag with the given `rel` attribute.
+///
+/// Links without a `rel` attribute count as "alternate", as per the atom spec.
+fn atom_link<'a>(links: Option<&'a Value>, rel: &str) -> Option<&'a Value> {
+    as_list(links).into_iter().find(|link| {
+        let link_rel = link
+            .get("@rel")
+            .and_then(Value::as_str)
+            .unwrap_or("alternate");
+        link_rel == rel
+    })
+}
+
+/// Inserts the value under the RSS name of the key.
+///
+/// Keys that atom and RSS share but with a different structure (like `link` and `author`)
+/// are overwritten, the others are only inserted if they're not already present.
+fn insert_rss_key(map: &mut Map<String, Value>, key: &str, val: Option<Value>) {
+    let Some(val) = val else {
+        return;
+    };
+
+    match key {
+        "link" | "author" => {
+            map.insert(key.to_string(), val);
+        }
+        _ => {
+            map.entry(key).or_insert(val);
+        }
+    }
+}
+
+/// Maps an atom `<feed>` to the same shape as an RSS `<channel>`.
+///
+/// The original atom keys are kept so they can still be used in patterns, but the
+/// RSS equivalents are added so that the rest of the program can treat both formats the same.
+fn atom_to_channel(mut feed: Value) -> Option<Value> {
+    let feed_map = feed.as_object_mut()?;
+    let entries = feed_map.remove("entry");
+
+    let mut channel = feed_map.clone();
+    let link = atom_link(feed_map.get("link"), "alternate").and_then(|link| link.get("@href"));
+    insert_rss_key(&mut channel, "link", link.cloned());
+    insert_rss_key(
+        &mut channel,
+        "description",
+        feed_map.get("subtitle").cloned(),
+    );
+    insert_rss_key(&mut channel, "copyright", feed_map.get("rights").cloned());
+    let image = feed_map.get("logo").or(feed_map.get("icon"));
+    insert_rss_key(&mut channel, "image", image.cloned());
+    let author = feed_map.get("author").and_then(|author| author.get("name"));
+    insert_rss_key(&mut channel, "author", author.cloned());
+
+    let items = as_list(entries.as_ref())
+        .into_iter()
+        .filter_map(Value::as_object)
+        .map(atom_entry_to_item)
+        .collect();
+
+    channel.insert("item".to_string(), Value::Array(items));
+
+    Some(Value::Object(channel))
+}
+
+/// Maps an atom `<entry>` to the same shape as an RSS `<item>`.
+fn atom_entry_to_item(entry: &Map<String, Value>) -> Value {
+    let mut item = entry.clone();
+
+    let enclosure = atom_link(entry.get("link"), "enclosure").map(|link| {
+        let mut enclosure = Map::new();
+        for (atom_key, rss_key) in [
+            ("@href", "@url"),
+            ("@type", "@type"),
+            ("@length", "@length"),
+        ] {
+            if let Some(val) = link.get(atom_key) {
+                enclosure.insert(rss_key.to_string(), val.clone());
+            }
+        }
+        Value::Object(enclosure)
+    });
+
+    let link = atom_link(entry.get("link"), "alternate").and_then(|link| link.get("@href"));
+    let published = entry.get("published").or(entry.get("updated"));
+    let description = entry.get("summary").or(entry.get("content"));
+    let author = entry.get("author").and_then(|author| author.get("name"));
+
+    insert_rss_key(&mut item, "enclosure", enclosure);
+    insert_rss_key(&mut item, "link", link.cloned());
+    insert_rss_key(&mut item, "guid", entry.get("id").cloned());
+    insert_rss_key(&mut item, "pubDate", published.cloned());
+    insert_rss_key(&mut item, "description", description.cloned());
+    insert_rss_key(&mut item, "author", author.cloned());
+
+    Value::Object(item)
 }
 
 /// Converts the podcast's xml string to serde values of the channel and the episodes.
 ///
+/// Both RSS and atom feeds are supported, atom feeds are mapped to the RSS structure.
+///
 /// The library will merge different namespaces together, which is why we manually change
-/// the itunes namespace, and then after converting it, we change it back. Preserving itunes:XXX as
-/// separate keys.
+/// the itunes and podcast namespaces, and then after converting it, we change it back. Preserving
+/// itunes:XXX and podcast:XXX as separate keys.
 fn xml_to_value(xml: &str, ui: &DownloadBar) -> Option<(RawPodcast, Vec<RawEpisode>)> {
     ui.log_info("converting xml to serde values");
     let placeholder = "__placeholder__";
-    let replacement = format!("itunes{}", placeholder);
-    let xml = xml.replace("itunes:", &replacement);
+    let namespaces = ["itunes", "podcast"];
+
+    let mut xml = xml.to_string();
+    for namespace in namespaces {
+        let replacement = format!("{}{}", namespace, placeholder);
+        xml = xml
+            .replace(&format!("<{}:", namespace), &format!("<{}", replacement))
+            .replace(&format!("</{}:", namespace), &format!("</{}", replacement));
+    }
+
+    let restore_key = |key: &str| {
+        let mut key = key.to_string();
+        for namespace in namespaces {
+            let replacement = format!("{}{}", namespace, placeholder);
+            key = key.replace(&replacement, &format!("{}:", namespace));
+        }
+        key
+    };
+
     let mut val = match get_inner_channel(xml) {
         Some(val) => val,
         None => {
-            ui.log_error("failed to find rss/channel xml tags");
+            ui.log_error("failed to find rss/channel or atom feed xml tags");
             return None;
         }
     };
@@ -48,22 +222,20 @@ fn xml_to_value(xml: &str, ui: &DownloadBar) -> Option<(RawPodcast, Vec<RawEpiso
 
     if let Some(obj) = val.as_object() {
         for (key, value) in obj {
-            let new_key = key.replace(&replacement, "itunes:");
-            new_map.insert(new_key, value.clone());
+            new_map.insert(restore_key(key), value.clone());
         }
     }
 
     let podcast = RawPodcast::new(new_map);
 
-    let items = std::mem::take(val.as_object_mut()?.get_mut("item")?.as_array_mut()?);
+    let items = std::mem::take(val.as_object_mut()?.get_mut("item")?);
 
-    let episodes = items
-        .iter()
+    let episodes = as_list(Some(&items))
+        .into_iter()
         .map(|item| {
             let mut new_item_map: Map<String, Value> = Map::new();
             for (key, val) in item.as_object().expect("unexpected serde type").iter() {
-                let new_key = key.replace(&replacement, "itunes:");
-                new_item_map.insert(new_key, val.clone());
+                new_item_map.insert(restore_key(key), val.clone());
             }
             RawEpisode::new(new_item_map)
         })
@@ -84,6 +256,11 @@ impl RawPodcast {
         utils::val_to_str(self.0.get(key)?)
     }
 
+    /// The text of the tag, including tags that were parsed as numbers.
+    pub fn get_text(&self, key: &str) -> Option<String> {
+        utils::val_to_string(self.0.get(key)?)
+    }
+
     pub fn title(&self) -> &str {
         self.get_str("title").unwrap()
     }
@@ -114,33 +291,110 @@ impl RawPodcast {
         let inner = self.0.get("image")?;
         utils::val_to_url(inner)
     }
+
+    /// The url the publisher says the feed has moved to.
+    pub fn new_feed_url(&self) -> Option<&str> {
+        let key = "itunes:new-feed-url";
+        self.get_str(key)
+    }
 }
 
 #[derive(Debug)]
 pub struct Podcast {
+    name: String,
+    config: PodcastConfig,
+    raw: RawPodcast,
     episodes: Vec<Episode>,
-    client: Arc<reqwest::Client>,
+    client: Arc<dyn HttpClient>,
+    limits: Arc<ConnectionLimits>,
+    throttle: Throttle,
     mode: DownloadMode,
+    parallel_episodes: usize,
+    /// The new url of the feed if it has moved.
+    new_url: Option<String>,
+    /// Episodes that were left out because their patterns had no value, with strict patterns.
+    unevaluated: Vec<Unevaluated>,
+}
+
+#[derive(Debug)]
+struct Unevaluated {
+    attrs: episode::Attributes,
+    index: usize,
+    error: String,
 }
 
 impl Podcast {
-    pub async fn new(
+    /// Fetches a single podcast on its own, outside of a sync.
+    ///
+    /// The `callbacks` are told about the progress of fetching the feed. Its episodes can
+    /// then be found with [`Podcast::episodes`] or [`Podcast::pending_episodes`], and
+    /// downloaded with [`Podcast::download`].
+    pub async fn fetch(
+        name: &str,
+        config: PodcastConfig,
+        global_config: &GlobalConfig,
+        fetch_mode: FetchMode,
+        callbacks: Box<dyn ProgressBackend>,
+    ) -> Result<Podcast, Error> {
+        let client = Arc::new(ReqwestClient::new(global_config)?);
+        Self::fetch_with_client(name, config, global_config, fetch_mode, client, callbacks).await
+    }
+
+    /// Like [`Podcast::fetch`], but the feed and its episodes are downloaded with `client`.
+    pub async fn fetch_with_client(
+        name: &str,
+        config: PodcastConfig,
+        global_config: &GlobalConfig,
+        fetch_mode: FetchMode,
+        client: Arc<dyn HttpClient>,
+        callbacks: Box<dyn ProgressBackend>,
+    ) -> Result<Podcast, Error> {
+        let limits = Arc::new(global_config.connection_limits()?);
+        let ui = DownloadBar::with_backend(name.to_string(), callbacks);
+
+        Self::new(
+            name.to_string(),
+            config,
+            global_config,
+            client,
+            limits,
+            fetch_mode,
+            &ui,
+        )
+        .await
+    }
+
+    pub(crate) async fn new(
         name: String,
         config: PodcastConfig,
         global_config: &GlobalConfig,
-        client: Arc<reqwest::Client>,
+        client: Arc<dyn HttpClient>,
+        limits: Arc<ConnectionLimits>,
+        fetch_mode: FetchMode,
         ui: &DownloadBar,
-    ) -> Result<Podcast, String> {
+    ) -> Result<Podcast, Error> {
         ui.fetching();
         ui.log_info("downloading podcast info...");
-        let Some(xml_string) = utils::download_text(&client, &config.url, ui).await else {
-            return Err("failed to download xml-file".into());
+        let retry = config.retry_policy(global_config);
+        let feed = {
+            let _permit = limits.host_permit(&config.url).await;
+            utils::download_text(client.as_ref(), &config.url, fetch_mode, &retry, ui).await
+        };
+        let Some(feed) = feed else {
+            return Err(Error::Network("failed to download xml-file".into()));
         };
 
-        let Some((raw_podcast, raw_episodes)) = xml_to_value(&xml_string, ui) else {
-            return Err("failed to parse xml".into());
+        let Some((raw_podcast, raw_episodes)) = xml_to_value(&feed.xml, ui) else {
+            return Err(Error::Network("failed to parse xml".into()));
         };
 
+        // An explicit new-feed-url takes precedence over a permanent redirect.
+        let new_url = raw_podcast
+            .new_feed_url()
+            .map(ToString::to_string)
+            .or(feed.moved_to)
+            .filter(|new_url| new_url != &config.url);
+
         let episode_attrs = {
             let mut attrs = vec![];
 
@@ -159,13 +413,26 @@ impl Podcast {
         };
 
         let mut episodes = vec![];
+        let mut unevaluated = vec![];
         for (index, attr) in episode_attrs.into_iter().enumerate() {
-            let tags = tags::extract_tags_from_raw(&raw_podcast, &attr, ui).await;
-            let config = {
-                let data = EvalData::new(&name, &raw_podcast, &attr);
-                Config::new(global_config, &config, data)
+            let data = EvalData::new(&name, &raw_podcast, &attr, index);
+            let config = match Config::new(global_config, &config, data) {
+                Ok(config) => config,
+                // Only fails the episode, the other episodes may have the missing value.
+                Err(EvalError::Missing(error)) => {
+                    ui.log_warn(&error);
+                    unevaluated.push(Unevaluated {
+                        attrs: attr,
+                        index,
+                        error,
+                    });
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
             };
 
+            let tags = tags::extract_tags_from_raw(&raw_podcast, &attr, ui).await;
+
             let url = attr
                 .image()
                 .ok()
@@ -176,48 +443,303 @@ impl Podcast {
             episodes.push(episode);
         }
 
-        let mode = DownloadMode::new(global_config, &config);
+        let mode = DownloadMode::new(global_config, &config)?;
+        let parallel_episodes = config.parallel_episodes(global_config);
+        let throttle = Throttle::new(
+            limits
+                .bandwidth()
+                .into_iter()
+                .chain(config.rate_limiter(global_config)?.map(Arc::new)),
+        );
 
         Ok(Podcast {
+            name,
+            config,
+            raw: raw_podcast,
             episodes,
             client,
+            limits,
+            throttle,
             mode,
+            parallel_episodes,
+            new_url,
+            unevaluated,
         })
     }
 
-    pub async fn sync(self, ui: &mut DownloadBar) -> Vec<PathBuf> {
+    /// The download directory, tracker and symlink directory of every episode, as if the
+    /// podcast was named `name`.
+    pub(crate) fn paths_as(
+        &self,
+        name: &str,
+        global_config: &GlobalConfig,
+    ) -> Result<Vec<EpisodePaths>, Error> {
+        self.episodes
+            .iter()
+            .map(|episode| {
+                let data = EvalData::new(name, &self.raw, &episode.attrs, episode.index);
+                let config = Config::new(global_config, &self.config, data)?;
+                Ok(EpisodePaths {
+                    download_dir: config.download_path,
+                    tracker: config.tracker_path,
+                    symlink_dir: config.symlink,
+                })
+            })
+            .collect()
+    }
+
+    pub fn new_url(&self) -> Option<&str> {
+        self.new_url.as_deref()
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Every episode of the podcast from oldest to newest.
+    pub fn episodes(&self) -> &[Episode] {
+        &self.episodes
+    }
+
+    /// Evaluates the pattern for one of the podcast's episodes, like its file name and paths.
+    pub fn evaluate(&self, pattern: &FullPattern, episode: &Episode) -> String {
+        let data = EvalData::new(&self.name, &self.raw, &episode.attrs, episode.index);
+        pattern.evaluate(data)
+    }
+
+    /// Downloads a single episode like a sync would, whether it's pending or not.
+    ///
+    /// The `callbacks` are told about the progress of the download, and the episode is
+    /// added to the download tracker once it's done.
+    pub async fn download(
+        &self,
+        episode: &Episode,
+        callbacks: Box<dyn ProgressBackend>,
+    ) -> Result<Record, Error> {
+        let mut ui = DownloadBar::with_backend(self.name.clone(), callbacks);
+        ui.init();
+        ui.begin_download(episode, 0, 1);
+
+        let result = episode
+            .download(self.client.as_ref(), &self.limits, &self.throttle, &ui)
+            .await;
+
+        match result {
+            Ok(mut downloaded) => {
+                ui.hook_status();
+                let hook_status = downloaded.await_handle(&ui).await;
+                ui.complete();
+                Ok(downloaded.into_record(&self.name, hook_status))
+            }
+            Err(e) => {
+                ui.error(&e);
+                Err(Error::Network(e))
+            }
+        }
+    }
+
+    /// Downloads the pending episodes and missing transcripts like a sync would.
+    ///
+    /// An episode that fails to download, or whose patterns have no value with
+    /// `strict_patterns`, doesn't stop the others. It's reported as a
+    /// [`Record::EpisodeError`] instead.
+    pub async fn download_pending(self, callbacks: Box<dyn ProgressBackend>) -> Vec<Record> {
+        let mut ui = DownloadBar::with_backend(self.name.clone(), callbacks);
+        self.sync(&mut ui).await
+    }
+
+    pub(crate) async fn sync(self, ui: &mut DownloadBar) -> Vec<Record> {
         ui.init();
         ui.log_info("syncing...");
 
         let episodes = self.pending_episodes();
-        let mut downloaded = vec![];
+        let transcripts = self.missing_transcripts();
+        let errors = self.unevaluated_errors();
+        self.download_episodes(episodes, transcripts, errors, ui)
+            .await
+    }
+
+    /// Downloads the selected episodes regardless of the download mode.
+    pub(crate) async fn download_selected(
+        self,
+        selection: &EpisodeSelection,
+        ui: &mut DownloadBar,
+    ) -> Vec<Record> {
+        ui.init();
+        ui.log_info("downloading selected episodes...");
+
+        let episodes: Vec<&Episode> = self
+            .episodes
+            .iter()
+            .filter(|episode| selection.matches(episode))
+            .filter(|episode| selection.force || !episode.is_downloaded())
+            .collect();
+
+        self.download_episodes(episodes, vec![], vec![], ui).await
+    }
 
-        for (index, episode) in episodes.iter().enumerate() {
-            ui.begin_download(&episode, index, episodes.len());
+    /// Downloads the episodes and transcripts, `errors` are the episodes that already failed.
+    async fn download_episodes(
+        &self,
+        episodes: Vec<&Episode>,
+        transcripts: Vec<&Episode>,
+        errors: Vec<Record>,
+        ui: &mut DownloadBar,
+    ) -> Vec<Record> {
+        let episode_qty = episodes.len();
+
+        if self.parallel_episodes > 1 {
+            ui.begin_parallel_download(episode_qty);
+        }
 
-            match episode.download(&self.client, ui).await {
+        let results: Vec<_> = {
+            let ui: &DownloadBar = ui;
+            let downloads: Vec<_> = episodes
+                .into_iter()
+                .enumerate()
+                .map(|(index, episode)| self.download_episode(episode, index, episode_qty, ui))
+                .collect();
+
+            stream::iter(downloads)
+                .buffered(self.parallel_episodes)
+                .collect()
+                .await
+        };
+
+        let mut downloaded = vec![];
+        let mut records = errors;
+
+        // A failing episode shouldn't stop the others from being downloaded.
+        for (episode, result) in results {
+            match result {
                 Ok(downloaded_episode) => downloaded.push(downloaded_episode),
                 Err(e) => {
-                    ui.error(&e);
-                    break;
+                    episode.log_error(ui, format!("failed to download episode: {}", &e));
+                    records.push(episode.error_record(&self.name, e));
                 }
             };
         }
 
-        let mut paths = vec![];
+        let error_qty = records.len();
+
+        for episode in transcripts {
+            episode.download_transcript(self.client.as_ref(), ui).await;
+        }
 
         ui.hook_status();
         for mut episode in downloaded {
-            episode.await_handle(ui).await;
-            paths.push(episode.into_path());
+            let hook_status = episode.await_handle(ui).await;
+            records.push(episode.into_record(&self.name, hook_status));
+        }
+
+        if error_qty == 0 {
+            ui.complete();
+        } else {
+            ui.complete_with_errors(error_qty);
         }
 
-        ui.complete();
-        paths
+        records
     }
 
-    fn pending_episodes(&self) -> Vec<&Episode> {
-        let qty = self.episodes.len();
+    /// Describes what syncing the podcast would do, without downloading anything.
+    pub fn plan(&self) -> String {
+        let mut lines = vec![self.name.clone()];
+
+        for episode in self.pending_episodes() {
+            lines.push(episode.plan());
+        }
+
+        for episode in self.missing_transcripts() {
+            lines.push(format!("  {} (missing transcript)", episode.attrs.title()));
+            lines.extend(episode.plan_transcript());
+        }
+
+        for episode in self.pending_unevaluated() {
+            lines.push(format!(
+                "  {} (failed: {})",
+                episode.attrs.title(),
+                episode.error
+            ));
+        }
+
+        if lines.len() == 1 {
+            lines.push("  nothing to download".to_string());
+        }
+
+        lines.join("\n")
+    }
+
+    async fn download_episode<'a>(
+        &'a self,
+        episode: &'a Episode,
+        index: usize,
+        episode_qty: usize,
+        ui: &DownloadBar,
+    ) -> (&'a Episode, Result<DownloadedEpisode<'a>, String>) {
+        // Parallel downloads share the bar, so it shows their combined progress instead.
+        if self.parallel_episodes == 1 {
+            ui.begin_download(episode, index, episode_qty);
+        }
+
+        let result = episode
+            .download(self.client.as_ref(), &self.limits, &self.throttle, ui)
+            .await;
+        (episode, result)
+    }
+
+    /// Downloaded episodes that still need their transcript fetched.
+    fn missing_transcripts(&self) -> Vec<&Episode> {
+        let qty = self.episode_qty();
+
+        self.episodes
+            .iter()
+            .filter(|episode| episode.is_missing_transcript(&self.mode, qty))
+            .collect()
+    }
+
+    /// How many episodes the feed has, including the ones left out.
+    fn episode_qty(&self) -> usize {
+        self.episodes.len() + self.unevaluated.len()
+    }
+
+    /// Left out episodes that a sync would've downloaded.
+    fn pending_unevaluated(&self) -> Vec<&Unevaluated> {
+        let qty = self.episode_qty();
+
+        self.unevaluated
+            .iter()
+            .filter(|episode| {
+                self.mode
+                    .includes(episode.attrs.published(), episode.index, qty)
+            })
+            .collect()
+    }
+
+    fn unevaluated_errors(&self) -> Vec<Record> {
+        self.pending_unevaluated()
+            .into_iter()
+            .map(|episode| Record::EpisodeError {
+                podcast: self.name.clone(),
+                title: episode.attrs.title().to_string(),
+                guid: episode.attrs.guid().to_string(),
+                error: episode.error.clone(),
+            })
+            .collect()
+    }
+
+    /// Every episode of the podcast from oldest to newest.
+    pub fn listings(&self) -> Vec<EpisodeListing> {
+        let qty = self.episode_qty();
+
+        self.episodes
+            .iter()
+            .map(|episode| episode.listing(&self.name, &self.mode, qty))
+            .collect()
+    }
+
+    /// The episodes that a sync would download, in the order it'd download them.
+    pub fn pending_episodes(&self) -> Vec<&Episode> {
+        let qty = self.episode_qty();
 
         let mut pending: Vec<&Episode> = self
             .episodes
@@ -240,3 +762,92 @@ impl Podcast {
         pending
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const ATOM: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <title>Atom Cast</title>
+  <subtitle>A podcast in atom</subtitle>
+  <rights>CC0</rights>
+  <logo>http://example.com/logo.png</logo>
+  <author><name>Jane</name></author>
+  <link rel="self" href="http://example.com/feed.atom"/>
+  <link href="http://example.com"/>
+  <entry>
+    <title>First</title>
+    <id>urn:first</id>
+    <updated>2024-01-01T10:00:00Z</updated>
+    <summary>The first one</summary>
+    <link rel="alternate" href="http://example.com/first"/>
+    <link rel="enclosure" href="http://example.com/first.mp3" type="audio/mpeg" length="123"/>
+  </entry>
+  <entry>
+    <title>Second</title>
+    <id>urn:second</id>
+    <published>2024-01-02T10:00:00Z</published>
+    <updated>2024-01-03T10:00:00Z</updated>
+    <content>The second one</content>
+    <link rel="enclosure" href="http://example.com/second.mp3"/>
+  </entry>
+</feed>"#;
+
+    #[test]
+    fn maps_atom_feed_to_channel() {
+        let channel = get_inner_channel(ATOM.to_string()).unwrap();
+
+        assert_eq!(channel["title"], "Atom Cast");
+        assert_eq!(channel["link"], "http://example.com");
+        assert_eq!(channel["description"], "A podcast in atom");
+        assert_eq!(channel["copyright"], "CC0");
+        assert_eq!(channel["image"], "http://example.com/logo.png");
+        assert_eq!(channel["author"], "Jane");
+        // The atom keys are still there for patterns.
+        assert_eq!(channel["subtitle"], "A podcast in atom");
+    }
+
+    #[test]
+    fn maps_atom_entries_to_items() {
+        let channel = get_inner_channel(ATOM.to_string()).unwrap();
+        let items = channel["item"].as_array().unwrap();
+
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0]["guid"], "urn:first");
+        assert_eq!(items[0]["pubDate"], "2024-01-01T10:00:00Z");
+        assert_eq!(items[0]["description"], "The first one");
+        assert_eq!(items[0]["link"], "http://example.com/first");
+        assert_eq!(
+            items[0]["enclosure"],
+            json!({"@url": "http://example.com/first.mp3", "@type": "audio/mpeg", "@length": 123})
+        );
+
+        // Published takes precedence over updated, and content is used without a summary.
+        assert_eq!(items[1]["pubDate"], "2024-01-02T10:00:00Z");
+        assert_eq!(items[1]["description"], "The second one");
+        assert_eq!(
+            items[1]["enclosure"],
+            json!({"@url": "http://example.com/second.mp3"})
+        );
+    }
+
+    #[test]
+    fn single_atom_entry_is_a_list() {
+        let xml = r#"<feed><title>One</title><entry><title>Only</title><id>1</id></entry></feed>"#;
+        let channel = get_inner_channel(xml.to_string()).unwrap();
+
+        assert_eq!(channel["item"].as_array().unwrap().len(), 1);
+        assert!(channel.get("link").is_none());
+    }
+
+    #[test]
+    fn rss_channel_is_returned_as_is() {
+        let xml = "<rss><channel><title>Rss</title><item><title>A</title></item></channel></rss>";
+        let channel = get_inner_channel(xml.to_string()).unwrap();
+
+        assert_eq!(channel["title"], "Rss");
+        assert_eq!(channel["item"]["title"], "A");
+    }
+}
@@ -0,0 +1,162 @@
+use crate::error::Error;
+use crate::utils;
+use serde::Serialize;
+use std::path::PathBuf;
+
+/// How the results of a sync are printed to stdout.
+#[derive(Debug, Clone, Copy, PartialEq, Default, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// The downloaded paths with `--print`, or a table when listing episodes.
+    #[default]
+    Text,
+    /// One JSON object per line, like a record for every downloaded episode and every error.
+    Json,
+}
+
+/// The outcome of syncing a single episode or podcast.
+#[derive(Serialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Record {
+    Episode {
+        podcast: String,
+        title: String,
+        guid: String,
+        /// The evaluated `id_pattern`.
+        id: String,
+        /// RFC 3339 formatted.
+        pubdate: String,
+        url: String,
+        path: PathBuf,
+        symlink: Option<PathBuf>,
+        bytes: u64,
+        /// The exit code of the download hook, if one ran and exited normally.
+        hook_status: Option<i32>,
+    },
+    EpisodeError {
+        podcast: String,
+        title: String,
+        guid: String,
+        error: String,
+    },
+    Error {
+        podcast: String,
+        error: Error,
+    },
+}
+
+impl Record {
+    /// Where the episode was downloaded to, if this is a downloaded episode.
+    pub fn path(&self) -> Option<&PathBuf> {
+        match self {
+            Self::Episode { path, .. } => Some(path),
+            _ => None,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("failed to serialize record")
+    }
+}
+
+/// Whether a sync of `podcast_qty` podcasts failed, judging by its records.
+///
+/// It's a network error if none of the podcasts could be fetched, otherwise a partial failure
+/// if any podcast or episode failed.
+pub fn sync_status(records: &[Record], podcast_qty: usize) -> Result<(), Error> {
+    let mut podcasts = 0;
+    let mut episodes = 0;
+    let mut unreachable = 0;
+
+    for record in records {
+        match record {
+            Record::Episode { .. } => {}
+            Record::EpisodeError { .. } => episodes += 1,
+            Record::Error { error, .. } => {
+                podcasts += 1;
+                if matches!(error, Error::Network(_)) {
+                    unreachable += 1;
+                }
+            }
+        }
+    }
+
+    if unreachable > 0 && unreachable == podcast_qty {
+        Err(Error::Network(format!(
+            "none of the {} podcasts could be fetched",
+            unreachable
+        )))
+    } else if podcasts + episodes > 0 {
+        Err(Error::Partial { podcasts, episodes })
+    } else {
+        Ok(())
+    }
+}
+
+/// An episode of a podcast as shown by `--episodes`.
+#[derive(Serialize, Debug)]
+pub struct EpisodeListing {
+    pub podcast: String,
+    /// The position of the episode when sorted by publish date, starting from 1.
+    pub index: usize,
+    /// RFC 3339 formatted.
+    pub pubdate: String,
+    pub title: String,
+    /// The `itunes:duration` as `HH:MM:SS`.
+    pub duration: Option<String>,
+    pub guid: String,
+    /// Whether the episode is in the download tracker.
+    pub downloaded: bool,
+    /// Whether the next sync would download the episode.
+    pub pending: bool,
+}
+
+impl EpisodeListing {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("failed to serialize episode")
+    }
+}
+
+/// Formats the episodes as a table, grouped by podcast.
+pub fn episode_table(episodes: &[EpisodeListing]) -> String {
+    const TITLE_WIDTH: usize = 40;
+
+    let mut lines = vec![];
+    let mut podcast = None;
+
+    for episode in episodes {
+        if podcast != Some(&episode.podcast) {
+            if podcast.is_some() {
+                lines.push(String::new());
+            }
+            podcast = Some(&episode.podcast);
+            lines.push(episode.podcast.clone());
+            lines.push(format!(
+                "  {:>5}  {:<10}  {:>8}  {:<10}  {:<7}  {:<title_width$}  guid",
+                "#",
+                "pubdate",
+                "duration",
+                "downloaded",
+                "pending",
+                "title",
+                title_width = TITLE_WIDTH
+            ));
+        }
+
+        let yes_no = |val: bool| if val { "yes" } else { "no" };
+        let title = utils::truncate_string(&episode.title, TITLE_WIDTH, true);
+
+        lines.push(format!(
+            "  {:>5}  {:<10}  {:>8}  {:<10}  {:<7}  {:<title_width$}  {}",
+            episode.index,
+            episode.pubdate.get(..10).unwrap_or_default(),
+            episode.duration.as_deref().unwrap_or("-"),
+            yes_no(episode.downloaded),
+            yes_no(episode.pending),
+            title,
+            &episode.guid,
+            title_width = TITLE_WIDTH
+        ));
+    }
+
+    lines.join("\n")
+}
@@ -0,0 +1,220 @@
+use crate::display::DownloadBar;
+use crate::http::{header, HttpError, Response, StatusCode};
+use std::future::Future;
+use std::time;
+
+/// How often and how long to wait before retrying a failed request.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// How many times a request is retried after the first attempt.
+    pub retries: u32,
+    /// The delay before the first retry, doubled for every following retry.
+    pub backoff: time::Duration,
+    /// The longest delay between two attempts, also caps the `Retry-After` header.
+    pub max_delay: time::Duration,
+}
+
+impl RetryPolicy {
+    /// The delay before retrying the request after the given (zero-indexed) attempt failed.
+    fn delay(&self, attempt: u32, retry_after: Option<time::Duration>) -> time::Duration {
+        let backoff = self
+            .backoff
+            .checked_mul(2u32.saturating_pow(attempt))
+            .unwrap_or(self.max_delay);
+
+        retry_after
+            .map_or(backoff, |retry_after| retry_after.max(backoff))
+            .min(self.max_delay)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            retries: 3,
+            backoff: time::Duration::from_millis(1000),
+            max_delay: time::Duration::from_millis(60_000),
+        }
+    }
+}
+
+/// The error of a single attempt at a request.
+#[derive(Debug)]
+pub enum RequestError {
+    /// Might succeed if we try again, like a timeout or an overloaded server.
+    Transient {
+        msg: String,
+        retry_after: Option<time::Duration>,
+    },
+    /// Retrying won't help.
+    Fatal(String),
+}
+
+impl RequestError {
+    pub fn transient(msg: impl Into<String>) -> Self {
+        Self::Transient {
+            msg: msg.into(),
+            retry_after: None,
+        }
+    }
+
+    pub fn fatal(msg: impl Into<String>) -> Self {
+        Self::Fatal(msg.into())
+    }
+
+    /// Errors from sending a request. Only an invalid request isn't worth retrying.
+    pub fn from_http(error: HttpError) -> Self {
+        match error {
+            HttpError::InvalidRequest(msg) => Self::Fatal(msg),
+            HttpError::Connection(msg) => Self::transient(msg),
+        }
+    }
+
+    /// Checks the status of a response.
+    ///
+    /// `429 Too Many Requests` and server errors are transient, and respect the
+    /// `Retry-After` header if the server sent one.
+    pub fn from_status(response: &Response) -> Result<(), Self> {
+        let status = response.status;
+        let msg = format!("server responded with status: {}", status);
+
+        if status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error() {
+            let retry_after = response
+                .headers
+                .get(header::RETRY_AFTER)
+                .and_then(|val| val.to_str().ok())
+                .and_then(parse_retry_after);
+
+            Err(Self::Transient { msg, retry_after })
+        } else if status.is_client_error() {
+            Err(Self::Fatal(msg))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn msg(&self) -> &str {
+        match self {
+            Self::Transient { msg, .. } => msg,
+            Self::Fatal(msg) => msg,
+        }
+    }
+}
+
+/// Parses the `Retry-After` header, which is either in seconds or an HTTP date.
+fn parse_retry_after(val: &str) -> Option<time::Duration> {
+    if let Ok(secs) = val.trim().parse::<u64>() {
+        return Some(time::Duration::from_secs(secs));
+    }
+
+    let date = chrono::DateTime::parse_from_rfc2822(val.trim()).ok()?;
+    let secs = date.timestamp() - chrono::Utc::now().timestamp();
+    Some(time::Duration::from_secs(secs.max(0) as u64))
+}
+
+/// Runs the request until it succeeds, fails fatally, or runs out of retries.
+pub async fn with_retries<T, F, Fut>(
+    policy: &RetryPolicy,
+    ui: &DownloadBar,
+    mut request: F,
+) -> Result<T, String>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, RequestError>>,
+{
+    let mut attempt = 0;
+
+    loop {
+        let (msg, retry_after) = match request().await {
+            Ok(val) => return Ok(val),
+            Err(RequestError::Transient { msg, retry_after }) if attempt < policy.retries => {
+                (msg, retry_after)
+            }
+            Err(e) => return Err(e.msg().to_string()),
+        };
+
+        let delay = policy.delay(attempt, retry_after);
+        attempt += 1;
+        ui.log_warn(format!(
+            "{}, retrying in {:?} ({}/{})",
+            msg, delay, attempt, policy.retries
+        ));
+        tokio::time::sleep(delay).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::HeaderMap;
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            retries: 5,
+            backoff: time::Duration::from_secs(1),
+            max_delay: time::Duration::from_secs(10),
+        }
+    }
+
+    fn response(status: StatusCode, retry_after: Option<&str>) -> Response {
+        let mut headers = HeaderMap::new();
+        if let Some(retry_after) = retry_after {
+            headers.insert(header::RETRY_AFTER, retry_after.parse().unwrap());
+        }
+        Response::new(status, headers, futures::stream::empty())
+    }
+
+    #[test]
+    fn delay_doubles_up_to_max() {
+        let delays: Vec<u64> = (0..6)
+            .map(|attempt| policy().delay(attempt, None).as_secs())
+            .collect();
+
+        assert_eq!(delays, [1, 2, 4, 8, 10, 10]);
+        assert_eq!(policy().delay(u32::MAX, None), policy().max_delay);
+    }
+
+    #[test]
+    fn delay_respects_retry_after() {
+        let secs = |secs| Some(time::Duration::from_secs(secs));
+
+        // Never sooner than the server asked for, nor the backoff.
+        assert_eq!(policy().delay(0, secs(5)), time::Duration::from_secs(5));
+        assert_eq!(policy().delay(2, secs(1)), time::Duration::from_secs(4));
+        // But never longer than the max delay.
+        assert_eq!(policy().delay(0, secs(3600)), policy().max_delay);
+    }
+
+    #[test]
+    fn retry_after_in_seconds_or_date() {
+        assert_eq!(
+            parse_retry_after(" 120 "),
+            Some(time::Duration::from_secs(120))
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(time::Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+
+    #[test]
+    fn status_decides_whether_to_retry() {
+        let transient =
+            |status, retry_after| match RequestError::from_status(&response(status, retry_after)) {
+                Err(RequestError::Transient { retry_after, .. }) => Some(retry_after),
+                _ => None,
+            };
+
+        assert_eq!(
+            transient(StatusCode::TOO_MANY_REQUESTS, Some("7")),
+            Some(Some(time::Duration::from_secs(7)))
+        );
+        assert_eq!(transient(StatusCode::BAD_GATEWAY, None), Some(None));
+        assert!(matches!(
+            RequestError::from_status(&response(StatusCode::NOT_FOUND, None)),
+            Err(RequestError::Fatal(_))
+        ));
+        assert!(RequestError::from_status(&response(StatusCode::OK, None)).is_ok());
+    }
+}
@@ -22,11 +22,9 @@ pub async fn extract_tags_from_raw(
 
     tags.set_genre("podcast");
 
-    if let Ok(episode) = episode.itunes_episode() {
-        if let Ok(episode) = episode.parse::<u32>() {
-            ui.log_trace("extracting itunes track number");
-            tags.set_track(episode);
-        }
+    if let Some(number) = episode.episode_number() {
+        ui.log_trace("extracting itunes track number");
+        tags.set_track(number);
     }
 
     let year = chrono::DateTime::from_timestamp(episode.published().as_secs() as i64, 0)
@@ -76,12 +74,9 @@ pub async fn extract_tags_from_raw(
         tags.set_text(Id3Tag::LANGUAGE, language);
     }
 
-    if let Ok(dur) = episode.itunes_duration() {
-        if let Ok(secs) = dur.parse::<u32>() {
-            ui.log_trace("extracting itunes duration tag");
-            let millis = secs * 1000;
-            tags.set_text(Id3Tag::DURATION, millis.to_string());
-        }
+    if let Some(duration) = episode.duration() {
+        ui.log_trace("extracting itunes duration tag");
+        tags.set_text(Id3Tag::DURATION, duration.as_millis().to_string());
     }
 
     if let Some(author) = podcast.author() {
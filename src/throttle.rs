@@ -0,0 +1,180 @@
+use chrono::NaiveTime;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+/// Parses a download rate like `"2MiB/s"` or `"500KB"` into bytes per second.
+pub fn parse_rate(s: &str) -> Option<u64> {
+    let s = s.trim();
+    let s = s.strip_suffix("/s").unwrap_or(s).trim();
+    let split = s
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(s.len());
+    let (num, unit) = s.split_at(split);
+    let num: f64 = num.parse().ok()?;
+
+    let multiplier: u64 = match unit.trim().to_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" => 1000,
+        "kib" => 1024,
+        "m" | "mb" => 1000_u64.pow(2),
+        "mib" => 1024_u64.pow(2),
+        "g" | "gb" => 1000_u64.pow(3),
+        "gib" => 1024_u64.pow(3),
+        _ => return None,
+    };
+
+    let rate = (num * multiplier as f64) as u64;
+    (rate > 0).then_some(rate)
+}
+
+/// A time of day range like `"23:00-07:00"`, which may wrap around midnight.
+#[derive(Debug, Clone, Copy)]
+pub struct Schedule {
+    start: NaiveTime,
+    end: NaiveTime,
+}
+
+impl Schedule {
+    pub fn from_str(s: &str) -> Option<Self> {
+        let (start, end) = s.split_once('-')?;
+        let parse = |time: &str| NaiveTime::parse_from_str(time.trim(), "%H:%M").ok();
+
+        Some(Self {
+            start: parse(start)?,
+            end: parse(end)?,
+        })
+    }
+
+    fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            self.start <= time || time < self.end
+        }
+    }
+
+    fn is_now(&self) -> bool {
+        self.contains(chrono::Local::now().time())
+    }
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+/// A token bucket limiting the combined rate of the downloads sharing it.
+#[derive(Debug)]
+pub struct RateLimiter {
+    /// Bytes per second, which is also how many bytes can be downloaded in a burst.
+    rate: u64,
+    /// When to download at full speed.
+    unthrottled: Option<Schedule>,
+    bucket: Mutex<Bucket>,
+}
+
+impl RateLimiter {
+    pub fn new(rate: u64, unthrottled: Option<Schedule>) -> Self {
+        Self {
+            rate,
+            unthrottled,
+            bucket: Mutex::new(Bucket {
+                tokens: rate as f64,
+                last_refill: Instant::now(),
+            }),
+        }
+    }
+
+    /// Waits until the bytes are allowed to be downloaded.
+    ///
+    /// The bucket stays locked while waiting, so downloads take turns in the order they asked.
+    async fn consume(&self, bytes: u64) {
+        if self.unthrottled.is_some_and(|schedule| schedule.is_now()) {
+            return;
+        }
+
+        let mut bucket = self.bucket.lock().await;
+        let now = Instant::now();
+        let refill = now.duration_since(bucket.last_refill).as_secs_f64() * self.rate as f64;
+        bucket.tokens = (bucket.tokens + refill).min(self.rate as f64) - bytes as f64;
+        bucket.last_refill = now;
+
+        if bucket.tokens < 0.0 {
+            let wait = -bucket.tokens / self.rate as f64;
+            tokio::time::sleep(std::time::Duration::from_secs_f64(wait)).await;
+            bucket.tokens = 0.0;
+            bucket.last_refill = Instant::now();
+        }
+    }
+}
+
+/// The rate limiters that apply to a download, like the global one and the one of the podcast.
+#[derive(Debug, Default, Clone)]
+pub struct Throttle(Vec<Arc<RateLimiter>>);
+
+impl Throttle {
+    pub fn new(limiters: impl IntoIterator<Item = Arc<RateLimiter>>) -> Self {
+        Self(limiters.into_iter().collect())
+    }
+
+    pub async fn consume(&self, bytes: u64) {
+        for limiter in &self.0 {
+            limiter.consume(bytes).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_rates() {
+        assert_eq!(parse_rate("2MiB/s"), Some(2 * 1024 * 1024));
+        assert_eq!(parse_rate("500KB"), Some(500_000));
+        assert_eq!(parse_rate(" 1.5 kib/s "), Some(1536));
+        assert_eq!(parse_rate("100"), Some(100));
+        assert_eq!(parse_rate("1g"), Some(1_000_000_000));
+    }
+
+    #[test]
+    fn rejects_invalid_rates() {
+        for rate in ["", "fast", "2 MiBs", "0", "0.0001b", "-1MB", "MB"] {
+            assert_eq!(parse_rate(rate), None, "{}", rate);
+        }
+    }
+
+    fn time(s: &str) -> NaiveTime {
+        NaiveTime::parse_from_str(s, "%H:%M").unwrap()
+    }
+
+    #[test]
+    fn schedule_within_a_day() {
+        let schedule = Schedule::from_str("09:00 - 17:30").unwrap();
+
+        assert!(schedule.contains(time("09:00")));
+        assert!(schedule.contains(time("17:29")));
+        assert!(!schedule.contains(time("17:30")));
+        assert!(!schedule.contains(time("08:59")));
+    }
+
+    #[test]
+    fn schedule_wraps_around_midnight() {
+        let schedule = Schedule::from_str("23:00-07:00").unwrap();
+
+        assert!(schedule.contains(time("23:00")));
+        assert!(schedule.contains(time("00:00")));
+        assert!(schedule.contains(time("06:59")));
+        assert!(!schedule.contains(time("07:00")));
+        assert!(!schedule.contains(time("12:00")));
+    }
+
+    #[test]
+    fn rejects_invalid_schedules() {
+        for schedule in ["", "23:00", "23:00-", "25:00-07:00", "night"] {
+            assert!(Schedule::from_str(schedule).is_none(), "{}", schedule);
+        }
+    }
+}
@@ -0,0 +1,287 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use toml_edit::DocumentMut;
+use toml_edit::Item;
+use toml_edit::Table;
+use toml_edit::Value;
+
+/// Serializes the value into the TOML document, changing only the keys whose value changed.
+///
+/// What changed is found by comparing the value with what the document deserializes to, so
+/// keys the value doesn't serialize, like unknown keys, aliases and skipped defaults, are
+/// never touched. Comments, ordering and formatting are kept, new keys are appended and
+/// keys whose value was removed are removed.
+pub fn merge<T>(original: &str, value: &T) -> Result<String, String>
+where
+    T: Serialize + DeserializeOwned,
+{
+    let new = to_document(value)?;
+
+    if original.trim().is_empty() {
+        return Ok(new.to_string());
+    }
+
+    let mut doc: DocumentMut = original.parse().map_err(|e| format!("{}", e))?;
+    let old: T = toml::from_str(original).map_err(|e| e.to_string())?;
+    let old = to_document(&old)?;
+
+    merge_tables(doc.as_table_mut(), old.as_table(), new.as_table());
+    Ok(doc.to_string())
+}
+
+/// Renames a top-level table, keeping its comments and position in the document.
+pub fn rename_table(original: &str, old_name: &str, new_name: &str) -> Result<String, String> {
+    let mut doc: DocumentMut = original.parse().map_err(|e| format!("{}", e))?;
+
+    let Some((_, item)) = doc.remove_entry(old_name) else {
+        return Err(format!("'{}' isn't in the document", old_name));
+    };
+
+    doc.insert(new_name, item);
+    Ok(doc.to_string())
+}
+
+fn to_document(value: &impl Serialize) -> Result<DocumentMut, String> {
+    let serialized = toml::to_string(value).map_err(|e| e.to_string())?;
+    serialized.parse().map_err(|e| format!("{}", e))
+}
+
+/// Applies the changes from `old` to `new` to the table in the document.
+fn merge_tables(doc: &mut Table, old: &Table, new: &Table) {
+    for (key, _) in old.iter() {
+        if !new.contains_key(key) {
+            doc.remove(key);
+        }
+    }
+
+    for (key, new_item) in new.iter() {
+        let old_item = old.get(key);
+        if old_item.is_some_and(|old_item| same_item(old_item, new_item)) {
+            continue;
+        }
+
+        match doc.get_mut(key) {
+            Some(doc_item) => merge_items(doc_item, old_item, new_item),
+            None => {
+                doc.insert(key, detached(new_item));
+            }
+        }
+    }
+}
+
+/// Copies the item without the position of its tables in the serialized document, so
+/// they're placed after the tables that precede them instead of by that position.
+fn detached(item: &Item) -> Item {
+    match item {
+        Item::Table(table) => {
+            let mut copy = Table::new();
+            copy.set_implicit(table.is_implicit());
+            for (key, item) in table.iter() {
+                copy.insert(key, detached(item));
+            }
+            Item::Table(copy)
+        }
+        item => item.clone(),
+    }
+}
+
+fn merge_items(doc: &mut Item, old: Option<&Item>, new: &Item) {
+    match (doc, new) {
+        (Item::Table(doc), Item::Table(new)) => {
+            let empty = Table::new();
+            let old = old.and_then(Item::as_table).unwrap_or(&empty);
+            merge_tables(doc, old, new);
+        }
+        // Tables that were written inline stay inline.
+        (Item::Value(doc @ Value::InlineTable(_)), Item::Table(new)) => {
+            let new = Value::InlineTable(new.clone().into_inline_table());
+            replace_value(doc, new);
+        }
+        (Item::Value(doc), Item::Value(new)) => replace_value(doc, new.clone()),
+        (doc, new) => *doc = detached(new),
+    }
+}
+
+/// Replaces the value, keeping the whitespace and comments around it.
+fn replace_value(doc: &mut Value, mut new: Value) {
+    *new.decor_mut() = doc.decor().clone();
+    *doc = new;
+}
+
+fn same_item(a: &Item, b: &Item) -> bool {
+    match (a, b) {
+        (Item::Value(a), Item::Value(b)) => same_value(a, b),
+        (Item::Table(a), Item::Table(b)) => {
+            a.len() == b.len()
+                && a.iter()
+                    .all(|(key, a)| b.get(key).is_some_and(|b| same_item(a, b)))
+        }
+        (Item::ArrayOfTables(a), Item::ArrayOfTables(b)) => {
+            a.len() == b.len()
+                && a.iter()
+                    .zip(b.iter())
+                    .all(|(a, b)| same_item(&Item::Table(a.clone()), &Item::Table(b.clone())))
+        }
+        (Item::None, Item::None) => true,
+        _ => false,
+    }
+}
+
+fn same_value(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::String(a), Value::String(b)) => a.value() == b.value(),
+        (Value::Integer(a), Value::Integer(b)) => a.value() == b.value(),
+        (Value::Float(a), Value::Float(b)) => a.value() == b.value(),
+        (Value::Boolean(a), Value::Boolean(b)) => a.value() == b.value(),
+        (Value::Datetime(a), Value::Datetime(b)) => a.value() == b.value(),
+        (Value::Array(a), Value::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| same_value(a, b))
+        }
+        (Value::InlineTable(a), Value::InlineTable(b)) => {
+            a.len() == b.len()
+                && a.iter()
+                    .all(|(key, a)| b.get(key).is_some_and(|b| same_value(a, b)))
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize, Deserialize, Default, Clone, PartialEq, Debug)]
+    struct Config {
+        #[serde(alias = "path")]
+        download_path: Option<String>,
+        #[serde(default, with = "level")]
+        level: String,
+        retries: Option<u32>,
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        tags: BTreeMap<String, String>,
+        #[serde(default, skip_serializing_if = "Nested::is_default")]
+        nested: Nested,
+    }
+
+    #[derive(Serialize, Deserialize, Default, Clone, PartialEq, Debug)]
+    struct Nested {
+        a: Option<i64>,
+        b: Option<i64>,
+    }
+
+    impl Nested {
+        fn is_default(&self) -> bool {
+            self == &Self::default()
+        }
+    }
+
+    /// Reads the level in any case and writes it in uppercase, like `log::LevelFilter`.
+    mod level {
+        use serde::{Deserialize, Deserializer, Serializer};
+
+        pub fn serialize<S: Serializer>(level: &str, s: S) -> Result<S::Ok, S::Error> {
+            s.serialize_str(&level.to_uppercase())
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<String, D::Error> {
+            Ok(String::deserialize(d)?.to_uppercase())
+        }
+    }
+
+    fn round_trip(original: &str, change: impl FnOnce(&mut Config)) -> String {
+        let mut config: Config = toml::from_str(original).unwrap();
+        change(&mut config);
+        merge(original, &config).unwrap()
+    }
+
+    #[test]
+    fn unchanged_document_is_kept_as_is() {
+        let original = "# my config\n\
+                        path = \"~/pods\" # where episodes go\n\
+                        level = \"info\"\n\
+                        unknown = 1\n\
+                        \n\
+                        [tags] # none yet\n\
+                        \n\
+                        [nested]\n\
+                        # also default\n";
+
+        assert_eq!(round_trip(original, |_| {}), original);
+    }
+
+    #[test]
+    fn changed_values_keep_comments() {
+        let original = "# my config\n\
+                        retries = 3 # a few\n\
+                        level = \"info\"\n";
+
+        let merged = round_trip(original, |config| config.retries = Some(5));
+
+        assert_eq!(
+            merged,
+            "# my config\nretries = 5 # a few\nlevel = \"info\"\n"
+        );
+    }
+
+    #[test]
+    fn new_keys_are_appended_in_order() {
+        let original = "# my config\nlevel = \"info\"\n";
+
+        let merged = round_trip(original, |config| {
+            config.retries = Some(1);
+            config.nested.b = Some(2);
+            config.tags.insert("artist".into(), "me".into());
+        });
+
+        assert_eq!(
+            merged,
+            "# my config\nlevel = \"info\"\nretries = 1\n\n[tags]\nartist = \"me\"\n\n[nested]\nb = 2\n"
+        );
+    }
+
+    #[test]
+    fn removed_values_are_removed() {
+        let original = "retries = 3\nlevel = \"info\"\n\n[nested]\na = 1 # first\nb = 2\n";
+
+        let merged = round_trip(original, |config| {
+            config.retries = None;
+            config.nested.b = None;
+        });
+
+        assert_eq!(merged, "level = \"info\"\n\n[nested]\na = 1 # first\n");
+    }
+
+    #[test]
+    fn aliases_are_kept() {
+        let original = "path = \"~/pods\"\n";
+
+        let merged = round_trip(original, |config| config.retries = Some(1));
+
+        assert_eq!(merged, "path = \"~/pods\"\nretries = 1\n");
+    }
+
+    #[test]
+    fn empty_document_gets_everything() {
+        let config = Config {
+            retries: Some(2),
+            ..Default::default()
+        };
+
+        assert_eq!(merge("", &config).unwrap(), "level = \"\"\nretries = 2\n");
+    }
+
+    #[test]
+    fn renamed_table_keeps_comments_and_position() {
+        let original = "# podcasts\n\n# the first\n[a]\nurl = \"a\" # feed\n\n[b]\nurl = \"b\"\n";
+
+        let renamed = rename_table(original, "a", "new name").unwrap();
+
+        assert_eq!(
+            renamed,
+            "# podcasts\n\n# the first\n[\"new name\"]\nurl = \"a\" # feed\n\n[b]\nurl = \"b\"\n"
+        );
+        assert!(rename_table(original, "c", "d").is_err());
+    }
+}
@@ -0,0 +1,294 @@
+use crate::display::DownloadBar;
+use crate::http::{self, HttpClient};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// The formats a `<podcast:transcript>` can be in.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TranscriptType {
+    Srt,
+    Vtt,
+    Json,
+    Html,
+    Txt,
+}
+
+impl TranscriptType {
+    fn from_mime(mime: &str) -> Option<Self> {
+        let ty = match mime {
+            "application/x-subrip" | "application/srt" | "text/srt" => Self::Srt,
+            "text/vtt" => Self::Vtt,
+            "application/json" => Self::Json,
+            "text/html" => Self::Html,
+            "text/plain" => Self::Txt,
+            _ => return None,
+        };
+
+        Some(ty)
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Srt => "srt",
+            Self::Vtt => "vtt",
+            Self::Json => "json",
+            Self::Html => "html",
+            Self::Txt => "txt",
+        }
+    }
+}
+
+/// A transcript listed in the feed of an episode.
+#[derive(Debug, Clone)]
+pub struct Transcript {
+    pub url: String,
+    pub ty: TranscriptType,
+}
+
+impl Transcript {
+    /// Parses a single `<podcast:transcript>` tag. Returns `None` for unsupported formats.
+    pub fn from_val(val: &serde_json::Value) -> Option<Self> {
+        let url = val.get("@url")?.as_str()?.to_string();
+        let ty = TranscriptType::from_mime(val.get("@type")?.as_str()?)?;
+
+        Some(Self { url, ty })
+    }
+
+    /// Picks the transcript to download.
+    ///
+    /// The first of the preferred types that's available will be chosen,
+    /// otherwise the first transcript in the feed is chosen.
+    pub fn select(transcripts: Vec<Self>, preferred: &[TranscriptType]) -> Option<Self> {
+        preferred
+            .iter()
+            .find_map(|ty| transcripts.iter().find(|transcript| &transcript.ty == ty))
+            .or(transcripts.first())
+            .cloned()
+    }
+
+    /// Downloads the transcript to the given path and returns its contents.
+    pub async fn download(
+        &self,
+        client: &dyn HttpClient,
+        path: &Path,
+        ui: &DownloadBar,
+    ) -> Result<String, String> {
+        ui.log_debug(format!("downloading transcript from: {}", &self.url));
+
+        let response = http::get(client, &self.url)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        if !response.status.is_success() {
+            return Err(format!("transcript url returned {}", response.status));
+        }
+
+        let text = response
+            .text()
+            .await
+            .map_err(|_| "failed to decode transcript".to_string())?;
+
+        std::fs::write(path, &text).map_err(|_| "failed to write transcript file".to_string())?;
+
+        Ok(text)
+    }
+
+    /// Converts the transcript into ID3v2 frames.
+    ///
+    /// All transcripts are added as an unsynchronised `USLT` frame, those with timestamps
+    /// are also added as a synchronised `SYLT` frame.
+    pub fn to_frames(&self, text: &str) -> Vec<id3::frame::Frame> {
+        use id3::frame::{
+            Content, Frame, Lyrics, SynchronisedLyrics, SynchronisedLyricsType, TimestampFormat,
+        };
+
+        let cues = match self.ty {
+            TranscriptType::Srt | TranscriptType::Vtt => parse_cues(text),
+            TranscriptType::Json => parse_json_segments(text),
+            TranscriptType::Html | TranscriptType::Txt => vec![],
+        };
+
+        let plain_text = match self.ty {
+            TranscriptType::Html => strip_html(text),
+            TranscriptType::Txt => text.to_string(),
+            _ => cues
+                .iter()
+                .map(|(_, line)| line.as_str())
+                .collect::<Vec<_>>()
+                .join("\n"),
+        };
+
+        // ID3v2 requires an ISO-639-2 code, "XXX" is used for unknown languages.
+        let lang = "XXX".to_string();
+
+        let mut frames = vec![Frame::with_content(
+            "USLT",
+            Content::Lyrics(Lyrics {
+                lang: lang.clone(),
+                description: "transcript".to_string(),
+                text: plain_text,
+            }),
+        )];
+
+        if !cues.is_empty() {
+            frames.push(Frame::with_content(
+                "SYLT",
+                Content::SynchronisedLyrics(SynchronisedLyrics {
+                    lang,
+                    timestamp_format: TimestampFormat::Ms,
+                    content_type: SynchronisedLyricsType::Transcription,
+                    description: "transcript".to_string(),
+                    content: cues,
+                }),
+            ));
+        }
+
+        frames
+    }
+}
+
+/// Parses a timestamp like `01:02:03,456` (SRT) or `02:03.456` (VTT) into milliseconds.
+fn parse_timestamp(s: &str) -> Option<u32> {
+    let s = s.split_whitespace().next()?.replace(',', ".");
+    let mut secs = 0.;
+
+    for part in s.split(':') {
+        secs = secs * 60. + part.parse::<f64>().ok()?;
+    }
+
+    Some((secs * 1000.) as u32)
+}
+
+/// Parses the cues of SRT and VTT files into their start time and text.
+fn parse_cues(text: &str) -> Vec<(u32, String)> {
+    let text = text.replace("\r\n", "\n");
+    let mut cues = vec![];
+
+    for block in text.split("\n\n") {
+        let mut lines = block.lines().skip_while(|line| !line.contains("-->"));
+
+        let Some(timing) = lines.next() else {
+            continue;
+        };
+
+        let Some(start) = timing.split("-->").next().and_then(parse_timestamp) else {
+            continue;
+        };
+
+        let line = lines.map(str::trim).collect::<Vec<_>>().join(" ");
+        cues.push((start, line));
+    }
+
+    cues
+}
+
+/// Parses the segments of the podcasting 2.0 JSON transcript format.
+fn parse_json_segments(text: &str) -> Vec<(u32, String)> {
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Segment {
+        start_time: f64,
+        body: String,
+    }
+
+    #[derive(Deserialize)]
+    struct JsonTranscript {
+        segments: Vec<Segment>,
+    }
+
+    match serde_json::from_str::<JsonTranscript>(text) {
+        Ok(transcript) => transcript
+            .segments
+            .into_iter()
+            .map(|segment| ((segment.start_time * 1000.) as u32, segment.body))
+            .collect(),
+        Err(_) => vec![],
+    }
+}
+
+pub fn strip_html(text: &str) -> String {
+    let tags = Regex::new(r"<[^>]*>").unwrap();
+    tags.replace_all(text, "").trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_srt_cues() {
+        let srt = "1\r\n00:00:01,500 --> 00:00:03,000\r\nHello\r\nthere\r\n\r\n\
+                   2\r\n01:02:03,456 --> 01:02:05,000\r\nGeneral Kenobi\r\n";
+
+        assert_eq!(
+            parse_cues(srt),
+            [
+                (1_500, "Hello there".to_string()),
+                (3_723_456, "General Kenobi".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_vtt_cues() {
+        let vtt = "WEBVTT\n\nNOTE a comment\n\nintro\n00:01.000 --> 00:02.000 align:start\n\
+                   <v Host>Welcome\n\n02:03.250 --> 02:04.000\nBye\n";
+
+        assert_eq!(
+            parse_cues(vtt),
+            [
+                (1_000, "<v Host>Welcome".to_string()),
+                (123_250, "Bye".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_cues_with_invalid_timestamps() {
+        let srt = "1\nsoon --> later\nNever\n\n2\n00:00:02,000 --> 00:00:03,000\nNow\n";
+
+        assert_eq!(parse_cues(srt), [(2_000, "Now".to_string())]);
+    }
+
+    #[test]
+    fn parses_json_segments() {
+        let json = r#"{"version": "1.0.0", "segments": [
+            {"speaker": "Host", "startTime": 0.5, "endTime": 1.0, "body": "Hi"},
+            {"startTime": 61.25, "endTime": 62, "body": "Bye"}
+        ]}"#;
+
+        assert_eq!(
+            parse_json_segments(json),
+            [(500, "Hi".to_string()), (61_250, "Bye".to_string())]
+        );
+        assert!(parse_json_segments("not json").is_empty());
+    }
+
+    #[test]
+    fn selects_preferred_transcript() {
+        let transcript = |ty| Transcript {
+            url: String::new(),
+            ty,
+        };
+        let available = vec![
+            transcript(TranscriptType::Html),
+            transcript(TranscriptType::Vtt),
+        ];
+
+        let select = |preferred: &[TranscriptType]| {
+            Transcript::select(available.clone(), preferred).map(|transcript| transcript.ty)
+        };
+
+        assert_eq!(
+            select(&[TranscriptType::Srt, TranscriptType::Vtt]),
+            Some(TranscriptType::Vtt)
+        );
+        assert_eq!(select(&[TranscriptType::Json]), Some(TranscriptType::Html));
+        assert_eq!(
+            Transcript::select(vec![], &[TranscriptType::Srt]).map(|t| t.ty),
+            None
+        );
+    }
+}
@@ -1,5 +1,13 @@
+use crate::cache;
 use crate::config;
 use crate::episode::Episode;
+use crate::error::Error;
+use crate::http::{
+    self, header, HttpClient, HttpError, Request, ReqwestClient, Response, StatusCode,
+};
+use crate::retry;
+use crate::retry::RequestError;
+use crate::retry::RetryPolicy;
 use crate::utils;
 use regex::Regex;
 use serde_json::Value;
@@ -10,7 +18,6 @@ use std::io::Write as IOWrite;
 use std::io::{BufRead, BufReader};
 use std::path::Path;
 use std::path::PathBuf;
-use std::process;
 use std::time;
 
 pub type Unix = std::time::Duration;
@@ -35,7 +42,8 @@ pub fn config_dir() -> PathBuf {
     }
     .join(crate::APPNAME);
 
-    utils::create_dir(&path);
+    // Failing to create it is reported once a file in it is accessed.
+    let _ = utils::create_dir(&path);
 
     path
 }
@@ -48,7 +56,8 @@ pub fn cache_dir() -> PathBuf {
     }
     .join(crate::APPNAME);
 
-    utils::create_dir(&path);
+    // Failing to create it is reported once a file in it is accessed.
+    let _ = utils::create_dir(&path);
 
     path
 }
@@ -62,7 +71,7 @@ pub fn default_download_path() -> PathBuf {
     let path = dirs::home_dir()
         .expect("unable to load home directory. Try hardcoding the download path in settings.")
         .join(crate::APPNAME);
-    utils::create_dir(&path);
+    let _ = utils::create_dir(&path);
     path
 }
 
@@ -94,29 +103,11 @@ pub fn truncate_string(s: &str, max_width: usize, append_dots: bool) -> String {
     truncated
 }
 
-pub fn short_handle_response(
+pub fn _handle_response(
     response: Result<reqwest::Response, reqwest::Error>,
-) -> Result<reqwest::Response, String> {
+) -> Result<reqwest::Response, Error> {
     match response {
         Ok(res) => Ok(res),
-        Err(e) => {
-            let error_message = match e {
-                e if e.is_builder() => format!("Invalid URL"),
-                e if e.is_connect() => format!("failed to connect to url",),
-                e if e.is_timeout() => format!("request timed out"),
-                e if e.is_status() => format!("server error"),
-                e if e.is_redirect() => format!("too many redirects while connecting"),
-                e if e.is_decode() => format!("failed to decode response"),
-                _ => format!("unexpected connection error"),
-            };
-            Err(error_message)
-        }
-    }
-}
-
-pub fn _handle_response(response: Result<reqwest::Response, reqwest::Error>) -> reqwest::Response {
-    match response {
-        Ok(res) => res,
         Err(e) => {
             let url = e.url().unwrap().clone();
 
@@ -132,8 +123,7 @@ pub fn _handle_response(response: Result<reqwest::Response, reqwest::Error>) ->
                 e if e.is_decode() => format!("Failed to decode response from URL: {}", url),
                 _ => format!("An unexpected error occurred: {}", e),
             };
-            eprintln!("{}", error_message);
-            process::exit(1);
+            Err(Error::Network(error_message))
         }
     }
 }
@@ -141,20 +131,112 @@ pub fn _handle_response(response: Result<reqwest::Response, reqwest::Error>) ->
 use crate::display::DownloadBar;
 use futures_util::StreamExt;
 
+/// How podcast feeds are fetched.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FetchMode {
+    /// Only download the feed if it changed since the last sync.
+    Conditional,
+    /// Always download the full feed.
+    ForceRefresh,
+    /// Never connect to the network, only use the cached feed.
+    Offline,
+}
+
+/// The xml of a podcast feed.
+pub struct FeedText {
+    pub xml: String,
+    /// The new url of the feed if the server permanently redirected us to it.
+    pub moved_to: Option<String>,
+}
+
+impl FeedText {
+    fn new(xml: String) -> Self {
+        Self {
+            xml,
+            moved_to: None,
+        }
+    }
+}
+
+/// Downloads the xml of a podcast feed.
+///
+/// In [`FetchMode::Conditional`] the request is conditional on the feed having changed since
+/// it was last downloaded, in which case the cached xml is returned.
+///
+/// The feed has only moved permanently if every redirect in the chain is permanent.
 pub async fn download_text(
-    client: &reqwest::Client,
+    client: &dyn HttpClient,
     url: &str,
+    mode: FetchMode,
+    retry: &RetryPolicy,
     ui: &DownloadBar,
-) -> Option<String> {
+) -> Option<FeedText> {
+    if mode == FetchMode::Offline {
+        ui.log_info("loading cached podcast xml");
+        let xml = cache::FeedCache::xml(url);
+        if xml.is_none() {
+            ui.log_error("no cached xml available in offline mode");
+        }
+        return xml.map(FeedText::new);
+    }
+
     ui.log_info("downloading podcast xml");
-    let response = match client.get(url).send().await {
-        Ok(res) => res,
+
+    let cached = cache::FeedCache::load(url).filter(|_| mode == FetchMode::Conditional);
+
+    match retry::with_retries(retry, ui, || fetch_feed(client, url, cached.as_ref(), ui)).await {
+        Ok(feed) => Some(feed),
         Err(e) => {
-            ui.log_error(&format!("connection failure: {:?}", e));
-            return None;
+            ui.log_error(format!("failed to download podcast xml: {}", e));
+            None
         }
+    }
+}
+
+/// A single attempt at downloading the xml of a podcast feed.
+async fn fetch_feed(
+    client: &dyn HttpClient,
+    url: &str,
+    cached: Option<&cache::FeedCache>,
+    ui: &DownloadBar,
+) -> Result<FeedText, RequestError> {
+    let request = Request::get(url).map_err(RequestError::from_http)?;
+    let request = match cached {
+        Some(cached) => cached.conditional_request(request),
+        None => request,
     };
 
+    let mut moved_to = None;
+    let mut permanent = true;
+
+    let response = http::follow_redirects(client, request, |status, location| {
+        permanent &= matches!(
+            status,
+            StatusCode::MOVED_PERMANENTLY | StatusCode::PERMANENT_REDIRECT
+        );
+
+        ui.log_debug(format!(
+            "podcast xml redirected ({}) to: {}",
+            status, location
+        ));
+        if permanent {
+            moved_to = Some(location.to_string());
+        }
+    })
+    .await
+    .map_err(RequestError::from_http)?;
+
+    if response.status == StatusCode::NOT_MODIFIED {
+        ui.log_info("podcast xml not modified, using cached xml");
+        return cache::FeedCache::xml(url)
+            .map(|xml| FeedText { xml, moved_to })
+            .ok_or_else(|| RequestError::fatal("failed to load cached xml"));
+    }
+
+    RequestError::from_status(&response)?;
+
+    let headers = response.headers.clone();
+    let response_ok = response.status.is_success();
     let total_size = response.content_length().unwrap_or(0);
 
     let mut downloaded = 0;
@@ -163,39 +245,44 @@ pub async fn download_text(
     ui.init_download_bar(downloaded, total_size);
     let mut buffer: Vec<u8> = vec![];
     while let Some(item) = stream.next().await {
-        let chunk = item.ok()?;
+        let chunk = item.map_err(RequestError::from_http)?;
         buffer.extend(&chunk);
         downloaded = std::cmp::min(downloaded + (chunk.len() as u64), total_size);
         ui.set_progress(downloaded);
     }
 
     match String::from_utf8(buffer) {
-        Ok(s) => Some(s),
-        Err(e) => {
-            ui.log_error(&format!("failed to decode xml: {:?}", e));
-            None
+        Ok(s) => {
+            if response_ok && cache::FeedCache::save(url, &s, &headers).is_none() {
+                ui.log_warn("failed to cache podcast xml");
+            }
+            Ok(FeedText { xml: s, moved_to })
         }
+        Err(e) => Err(RequestError::fatal(format!(
+            "failed to decode xml: {:?}",
+            e
+        ))),
     }
 }
 
-pub fn edit_file(path: &Path) {
+pub fn edit_file(path: &Path) -> Result<(), Error> {
     if !path.exists() {
         eprintln!("error: path does not exist: {:?}", path);
     }
 
-    let editor = match std::env::var("EDITOR") {
-        Ok(editor) => editor,
-        Err(_) => {
-            eprintln!("Unable to edit {:?}", path);
-            eprintln!("Please configure your $EDITOR environment variable");
-            std::process::exit(1);
-        }
+    let Ok(editor) = std::env::var("EDITOR") else {
+        return Err(Error::Usage(format!(
+            "Unable to edit {:?}\nPlease configure your $EDITOR environment variable",
+            path
+        )));
     };
 
     std::process::Command::new(editor)
-        .arg(path.to_str().unwrap())
+        .arg(path)
         .status()
-        .unwrap();
+        .map_err(Error::io(path))?;
+
+    Ok(())
 }
 
 pub fn replacer(val: Value, input: &str) -> String {
@@ -254,8 +341,13 @@ pub fn get_input(prompt: Option<&str>) -> Option<String> {
     }
 }
 
-pub async fn search_podcasts(config: &config::GlobalConfig, query: String, catch_up: bool) {
-    let response = search(&query).await;
+pub async fn search_podcasts(
+    config: &config::GlobalConfig,
+    query: String,
+    catch_up: bool,
+) -> Result<(), Error> {
+    let client = ReqwestClient::new(config)?;
+    let response = search(&client, &query).await?;
     let mut results = vec![];
 
     let mut idx = 0;
@@ -269,7 +361,7 @@ pub async fn search_podcasts(config: &config::GlobalConfig, query: String, catch
 
     if results.is_empty() {
         eprintln!("no podcasts matched your query.");
-        return;
+        return Ok(());
     }
 
     eprintln!("Enter index of podcast to add");
@@ -281,22 +373,20 @@ pub async fn search_podcasts(config: &config::GlobalConfig, query: String, catch
     }
 
     let Some(input) = get_input(None) else {
-        return;
+        return Ok(());
     };
 
     let mut indices = vec![];
     for input in input.split(" ") {
         let Ok(num) = input.parse::<usize>() else {
-            eprintln!(
+            return Err(Error::Usage(format!(
                 "invalid input: {}. You must enter the index of a podcast",
                 input
-            );
-            return;
+            )));
         };
 
         if num > results.len() || num == 0 {
-            eprintln!("index {} is out of bounds", num);
-            return;
+            return Err(Error::Usage(format!("index {} is out of bounds", num)));
         }
 
         indices.push(num - 1);
@@ -317,10 +407,10 @@ pub async fn search_podcasts(config: &config::GlobalConfig, query: String, catch
 
         let podcast = config::PodcastConfig::new(url);
 
-        if config::PodcastConfigs::push(name.clone(), podcast) {
+        if config::PodcastConfigs::push(name.clone(), podcast)? {
             eprintln!("'{}' added!", name);
             if catch_up {
-                regex_parts.push(format!("^{}$", &name));
+                regex_parts.push(format!("^{}$", regex::escape(&name)));
             }
         } else {
             eprintln!("'{}' already exists!", name);
@@ -329,9 +419,11 @@ pub async fn search_podcasts(config: &config::GlobalConfig, query: String, catch
 
     if catch_up && !regex_parts.is_empty() {
         let regex = regex_parts.join("|");
-        let filter = Regex::new(&regex).unwrap();
-        config::PodcastConfigs::catch_up(Some(filter));
+        let filter = Regex::new(&regex).expect("escaped names are valid regexes");
+        config::PodcastConfigs::catch_up(Some(filter))?;
     }
+
+    Ok(())
 }
 
 pub fn trim_quotes(s: &str) -> String {
@@ -349,31 +441,9 @@ pub fn date_str_to_unix(date: &str) -> Result<time::Duration, String> {
     Ok(time::Duration::from_secs(secs as u64))
 }
 
-pub fn get_extension_from_response(response: &reqwest::Response, episode: &Episode) -> String {
-    let url = &episode.attrs.url();
-    let ext = match PathBuf::from(url)
-        .extension()
-        .and_then(|ext| ext.to_str().map(String::from))
-    {
-        Some(ext) => ext.to_string(),
-        None => {
-            let content_type = response
-                .headers()
-                .get(reqwest::header::CONTENT_TYPE)
-                .and_then(|ct| ct.to_str().ok())
-                .unwrap_or("application/octet-stream");
-
-            let extensions = mime_guess::get_mime_extensions_str(&content_type).unwrap();
-
-            match extensions.contains(&"mp3") {
-                true => "mp3".to_owned(),
-                false => extensions
-                    .first()
-                    .expect("extension not found.")
-                    .to_string(),
-            }
-        }
-    };
+/// The extension of the file in the url, if it has one.
+pub fn extension_from_url(url: &str) -> Option<String> {
+    let ext = PathBuf::from(url).extension()?.to_str()?.to_string();
 
     // Some urls have these arguments after the extension.
     // feels a bit hacky.
@@ -382,26 +452,87 @@ pub fn get_extension_from_response(response: &reqwest::Response, episode: &Episo
         .split_once("?")
         .map(|(l, _)| l.to_string())
         .unwrap_or(ext);
-    ext
+    Some(ext)
+}
+
+/// The extension for the mime type, preferring `mp3` for audio that has several.
+pub fn extension_from_mime(mime: &str) -> Option<String> {
+    let extensions = mime_guess::get_mime_extensions_str(mime)?;
+
+    match extensions.contains(&"mp3") {
+        true => Some("mp3".to_owned()),
+        false => extensions.first().map(ToString::to_string),
+    }
+}
+
+/// The extension of files whose format can't be told from their url or mime type.
+pub const DEFAULT_EXTENSION: &str = "mp3";
+
+/// The extension of the downloaded enclosure, from its url, the `Content-Type` of the
+/// response or the mime type in the feed, in that order.
+pub fn get_extension_from_response(response: &Response, episode: &Episode) -> Option<String> {
+    extension_from_url(episode.attrs.url())
+        .or_else(|| {
+            let content_type = response.headers.get(header::CONTENT_TYPE)?;
+            extension_from_mime(content_type.to_str().ok()?)
+        })
+        .or_else(|| episode.attrs.extension())
+}
+
+/// Parses the `<itunes:duration>` of an episode, which is either a number of seconds or
+/// `[[HH:]MM:]SS`, optionally with a fraction of a second.
+pub fn parse_duration(duration: &str) -> Option<time::Duration> {
+    let parts: Vec<&str> = duration.trim().split(':').collect();
+    if parts.len() > 3 {
+        return None;
+    }
+
+    let (seconds, minutes_and_hours) = parts.split_last()?;
+    let seconds: f64 = seconds
+        .parse()
+        .ok()
+        .filter(|secs: &f64| secs.is_finite() && *secs >= 0.0)?;
+
+    let mut total: u64 = 0;
+    for part in minutes_and_hours {
+        total = total
+            .checked_mul(60)?
+            .checked_add(part.parse::<u64>().ok()?)?;
+    }
+
+    let total = total.checked_mul(60)?.checked_add(seconds as u64)?;
+    Some(time::Duration::from_secs(total))
+}
+
+/// Formats the duration as `HH:MM:SS`.
+pub fn format_duration(duration: time::Duration) -> String {
+    let secs = duration.as_secs();
+    format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
 }
 
 use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
 
-pub async fn search(terms: &str) -> Vec<Value> {
+pub async fn search(client: &dyn HttpClient, terms: &str) -> Result<Vec<Value>, Error> {
     let encoded: String = utf8_percent_encode(terms, NON_ALPHANUMERIC).to_string();
     let url = format!(
         "https://itunes.apple.com/search?media=podcast&entity=podcast&term={}",
         encoded
     );
-    let resp = reqwest::get(&url).await.unwrap().text().await.unwrap();
+    let failed = |e: HttpError| Error::Network(format!("failed to search podcasts: {}", e));
+    let resp = http::get(client, &url)
+        .await
+        .map_err(failed)?
+        .text()
+        .await
+        .map_err(failed)?;
 
     serde_json::from_str::<serde_json::Value>(&resp)
-        .unwrap()
-        .get("results")
-        .unwrap()
-        .as_array()
-        .unwrap()
-        .clone()
+        .ok()
+        .as_ref()
+        .and_then(|val| val.get("results"))
+        .and_then(Value::as_array)
+        .cloned()
+        .ok_or_else(|| Error::Network("unexpected response from the podcast search".into()))
 }
 
 pub fn val_to_str<'a>(val: &'a serde_json::Value) -> Option<&'a str> {
@@ -417,6 +548,17 @@ pub fn val_to_str<'a>(val: &'a serde_json::Value) -> Option<&'a str> {
     obj.get("#text")?.as_str()
 }
 
+/// Like [`val_to_str`], but numbers and booleans are formatted too, as the feed parser turns
+/// tags like `<itunes:episode>` into numbers.
+pub fn val_to_string(val: &serde_json::Value) -> Option<String> {
+    match val {
+        Value::Number(number) => Some(number.to_string()),
+        Value::Bool(bool) => Some(bool.to_string()),
+        Value::Object(obj) => val_to_string(obj.get("@text").or(obj.get("#text"))?),
+        _ => val.as_str().map(ToString::to_string),
+    }
+}
+
 pub fn val_to_url<'a>(val: &'a serde_json::Value) -> Option<&'a str> {
     if let Some(val) = val.as_str() {
         return Some(val);
@@ -428,6 +570,10 @@ pub fn val_to_url<'a>(val: &'a serde_json::Value) -> Option<&'a str> {
         return url.as_str();
     }
 
+    if let Some(url) = obj.get("@url") {
+        return url.as_str();
+    }
+
     if let Some(url) = obj.get("@href") {
         return url.as_str();
     }
@@ -477,12 +623,8 @@ pub fn append_to_config(file_path: &Path, key: &str, value: &str) -> io::Result<
     Ok(())
 }
 
-pub fn create_dir(path: &Path) {
-    if let Err(e) = fs::create_dir_all(path) {
-        eprintln!("failed to create following directory: {:?}", path);
-        eprintln!("error: {:?}", e);
-        process::exit(1);
-    }
+pub fn create_dir(path: &Path) -> Result<(), Error> {
+    fs::create_dir_all(path).map_err(Error::io(path))
 }
 
 pub fn _log_error<E: std::fmt::Debug>(ui: &DownloadBar, msg: &str, error: E) -> E {
@@ -496,3 +638,94 @@ pub fn _log_warn<E: std::fmt::Debug>(ui: &DownloadBar, msg: &str, error: E) -> E
     ui.log_warn(&msg);
     error
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::episode::{Attributes, RawEpisode};
+    use crate::http::{HeaderMap, StatusCode};
+    use serde_json::json;
+
+    #[test]
+    fn durations_are_normalized() {
+        for (duration, secs) in [
+            ("3723", 3723),
+            ("3723.6", 3723),
+            ("62:03", 3723),
+            ("1:02:03", 3723),
+            (" 01:02:03 ", 3723),
+            ("0:45", 45),
+        ] {
+            assert_eq!(
+                parse_duration(duration),
+                Some(time::Duration::from_secs(secs)),
+                "{}",
+                duration
+            );
+        }
+
+        for duration in ["", "abc", "1:2:3:4", "-5", "1:-2", "999999999999999999:0:0"] {
+            assert_eq!(parse_duration(duration), None, "{}", duration);
+        }
+
+        assert_eq!(
+            format_duration(time::Duration::from_secs(100 * 3600 + 5)),
+            "100:00:05"
+        );
+    }
+
+    fn enclosure(url: &str, mime: Option<&str>) -> Episode {
+        let mut enclosure = json!({ "@url": url });
+        if let Some(mime) = mime {
+            enclosure["@type"] = json!(mime);
+        }
+
+        let raw = json!({
+            "title": "episode",
+            "guid": "episode",
+            "pubDate": "Mon, 01 Jan 2024 10:00:00 +0000",
+            "enclosure": enclosure,
+        });
+        let raw = RawEpisode::new(raw.as_object().unwrap().clone());
+        Episode::new(
+            Attributes::new(raw).unwrap(),
+            0,
+            Config::default(),
+            None,
+            None,
+        )
+    }
+
+    fn extension(content_type: Option<&str>, episode: &Episode) -> Option<String> {
+        let mut headers = HeaderMap::new();
+        if let Some(content_type) = content_type {
+            headers.insert(header::CONTENT_TYPE, content_type.parse().unwrap());
+        }
+        let response = Response::new(StatusCode::OK, headers, futures::stream::empty());
+        get_extension_from_response(&response, episode)
+    }
+
+    #[test]
+    fn extension_is_found_from_url_then_response_then_feed() {
+        let episode = enclosure("http://example.com/episode.m4a?a=b", Some("audio/mpeg"));
+        assert_eq!(
+            extension(Some("audio/ogg"), &episode).as_deref(),
+            Some("m4a")
+        );
+
+        let episode = enclosure("http://example.com/episode", Some("video/mp4"));
+        assert_eq!(
+            extension(Some("audio/mpeg"), &episode).as_deref(),
+            Some("mp3")
+        );
+        assert_eq!(
+            extension(Some("nonsense/type"), &episode).as_deref(),
+            Some("mp4")
+        );
+        assert_eq!(extension(None, &episode).as_deref(), Some("mp4"));
+
+        let episode = enclosure("http://example.com/episode", None);
+        assert_eq!(extension(Some("nonsense/type"), &episode), None);
+    }
+}
@@ -0,0 +1,379 @@
+//! An in-process server for the integration tests, serving fixture feeds and enclosures
+//! through the [`HttpClient`] trait, or over a loopback connection, so the tests never
+//! touch the network.
+
+#![allow(dead_code)]
+
+use futures::future::BoxFuture;
+use futures::stream;
+use futures::StreamExt;
+use std::collections::{HashMap, VecDeque};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use talecast::http::{header, Bytes, HeaderMap, HeaderName, HeaderValue, HttpError};
+use talecast::http::{HttpClient, Request, Response, StatusCode};
+use talecast::{Episode, Error, FetchMode, GlobalConfig, Podcast, PodcastConfig, ProgressBackend};
+use tempfile::TempDir;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+
+pub const BASE_URL: &str = "http://fixtures.test";
+
+/// Bodies are streamed in chunks of this size.
+const CHUNK_SIZE: usize = 1024;
+
+/// How the server responds to a path.
+#[derive(Clone)]
+pub enum Route {
+    /// Responds with the body, or the requested part of it if `ranges` is set.
+    File { body: Vec<u8>, ranges: bool },
+    /// Redirects to another path.
+    Redirect { status: StatusCode, path: String },
+    /// Responds with an empty body.
+    Status(StatusCode),
+    /// Like a `File` with ranges, but the connection drops once `after` bytes of the whole
+    /// body were sent.
+    Interrupted { body: Vec<u8>, after: usize },
+}
+
+impl Route {
+    pub fn file(body: &[u8]) -> Self {
+        Self::File {
+            body: body.to_vec(),
+            ranges: true,
+        }
+    }
+
+    pub fn redirect(status: StatusCode, path: &str) -> Self {
+        Self::Redirect {
+            status,
+            path: path.to_string(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Routes {
+    /// Served once each, before the regular route of the path.
+    once: HashMap<String, VecDeque<Route>>,
+    regular: HashMap<String, Route>,
+}
+
+/// Serves [`Route`]s by path, and remembers every request it got.
+#[derive(Default)]
+pub struct FixtureServer {
+    routes: Mutex<Routes>,
+    requests: Mutex<Vec<Request>>,
+}
+
+impl FixtureServer {
+    pub fn new() -> Arc<Self> {
+        Arc::default()
+    }
+
+    pub fn serve(&self, path: &str, route: Route) {
+        let mut routes = self.routes.lock().unwrap();
+        routes.regular.insert(path.to_string(), route);
+    }
+
+    /// Serves the route on the next request to the path, before any other route of it.
+    pub fn serve_once(&self, path: &str, route: Route) {
+        let mut routes = self.routes.lock().unwrap();
+        routes
+            .once
+            .entry(path.to_string())
+            .or_default()
+            .push_back(route);
+    }
+
+    /// The requests made to the path, in order.
+    pub fn requests(&self, path: &str) -> Vec<Request> {
+        self.requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|request| request.url.path() == path)
+            .cloned()
+            .collect()
+    }
+
+    /// The `Range` headers of the requests made to the path, in order.
+    pub fn ranges(&self, path: &str) -> Vec<Option<String>> {
+        self.requests(path)
+            .iter()
+            .map(|request| {
+                request
+                    .headers
+                    .get(header::RANGE)
+                    .and_then(|range| range.to_str().ok())
+                    .map(ToString::to_string)
+            })
+            .collect()
+    }
+
+    fn route(&self, path: &str) -> Option<Route> {
+        let mut routes = self.routes.lock().unwrap();
+        if let Some(route) = routes.once.get_mut(path).and_then(VecDeque::pop_front) {
+            return Some(route);
+        }
+        routes.regular.get(path).cloned()
+    }
+
+    fn respond(&self, request: &Request) -> Response {
+        let Some(route) = self.route(request.url.path()) else {
+            return empty_response(StatusCode::NOT_FOUND, HeaderMap::new());
+        };
+
+        let start = requested_start(request);
+
+        match route {
+            Route::Redirect { status, path } => {
+                let mut headers = HeaderMap::new();
+                headers.insert(header::LOCATION, HeaderValue::from_str(&path).unwrap());
+                empty_response(status, headers)
+            }
+            Route::Status(status) => empty_response(status, HeaderMap::new()),
+            Route::File {
+                body,
+                ranges: false,
+            } => file_response(&body, None, body.len()),
+            Route::File { body, ranges: true } => match start {
+                Some(start) if start >= body.len() => {
+                    empty_response(StatusCode::RANGE_NOT_SATISFIABLE, HeaderMap::new())
+                }
+                start => file_response(&body, start, body.len()),
+            },
+            Route::Interrupted { body, after } => file_response(&body, start, after),
+        }
+    }
+}
+
+impl HttpClient for FixtureServer {
+    fn send(&self, request: Request) -> BoxFuture<'_, Result<Response, HttpError>> {
+        let response = self.respond(&request);
+        self.requests.lock().unwrap().push(request);
+        Box::pin(async move { Ok(response) })
+    }
+}
+
+/// Serves the routes of a [`FixtureServer`] over real connections on a loopback port, for
+/// testing the `ReqwestClient` that the fixture server replaces elsewhere.
+pub struct LoopbackServer {
+    addr: SocketAddr,
+}
+
+impl LoopbackServer {
+    pub async fn start(fixtures: Arc<FixtureServer>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve_connection(stream, fixtures.clone()));
+            }
+        });
+
+        Self { addr }
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.addr, path)
+    }
+}
+
+/// Answers a single request with the fixture server, then closes the connection.
+async fn serve_connection(stream: TcpStream, fixtures: Arc<FixtureServer>) {
+    let mut stream = BufReader::new(stream);
+
+    let mut request_line = String::new();
+    stream.read_line(&mut request_line).await.unwrap();
+    let path = request_line.split(' ').nth(1).unwrap_or("/");
+    let mut request = Request::get(&url(path)).unwrap();
+
+    loop {
+        let mut line = String::new();
+        stream.read_line(&mut line).await.unwrap();
+        let Some((name, value)) = line.trim_end().split_once(": ") else {
+            break;
+        };
+        request = request.header(name.parse::<HeaderName>().unwrap(), value);
+    }
+
+    let response = fixtures.send(request).await.unwrap();
+    let mut head = format!(
+        "HTTP/1.1 {} {}\r\n",
+        response.status.as_u16(),
+        response.status.canonical_reason().unwrap_or_default()
+    );
+    for (name, value) in &response.headers {
+        head.push_str(&format!("{}: {}\r\n", name, value.to_str().unwrap()));
+    }
+    head.push_str("connection: close\r\n\r\n");
+
+    let mut stream = stream.into_inner();
+    stream.write_all(head.as_bytes()).await.unwrap();
+
+    // An error in the body drops the connection, like an interrupted download.
+    let mut body = response.bytes_stream();
+    while let Some(Ok(chunk)) = body.next().await {
+        if stream.write_all(&chunk).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// The start of a `Range: bytes=<start>-` header.
+fn requested_start(request: &Request) -> Option<usize> {
+    request
+        .headers
+        .get(header::RANGE)?
+        .to_str()
+        .ok()?
+        .strip_prefix("bytes=")?
+        .strip_suffix('-')?
+        .parse()
+        .ok()
+}
+
+fn empty_response(status: StatusCode, headers: HeaderMap) -> Response {
+    Response::new(status, headers, stream::empty())
+}
+
+/// Responds with the body from `start`, but stops with an error if it reaches `end` first.
+fn file_response(body: &[u8], start: Option<usize>, end: usize) -> Response {
+    let mut headers = HeaderMap::new();
+    let status = match start {
+        Some(start) => {
+            let range = format!("bytes {}-{}/{}", start, body.len() - 1, body.len());
+            headers.insert(header::CONTENT_RANGE, range.parse().unwrap());
+            StatusCode::PARTIAL_CONTENT
+        }
+        None => StatusCode::OK,
+    };
+
+    let start = start.unwrap_or(0);
+    headers.insert(header::CONTENT_LENGTH, (body.len() - start).into());
+    headers.insert(header::CONTENT_TYPE, "audio/mp4".parse().unwrap());
+
+    let mut chunks: Vec<Result<Bytes, HttpError>> = body[start..end.max(start)]
+        .chunks(CHUNK_SIZE)
+        .map(|chunk| Ok(Bytes::copy_from_slice(chunk)))
+        .collect();
+
+    if end < body.len() {
+        chunks.push(Err(HttpError::Connection("connection reset".into())));
+    }
+
+    Response::new(status, headers, stream::iter(chunks))
+}
+
+pub fn url(path: &str) -> String {
+    format!("{}{}", BASE_URL, path)
+}
+
+/// An enclosure of `len` bytes that differ from the ones of other enclosures.
+pub fn enclosure(len: usize, seed: u8) -> Vec<u8> {
+    (0..len)
+        .map(|i| (i as u8).wrapping_mul(31) ^ seed)
+        .collect()
+}
+
+/// A feed with an episode for each `(title, enclosure path)`, from oldest to newest.
+pub fn feed(episodes: &[(&str, &str)]) -> Vec<u8> {
+    feed_at(BASE_URL, episodes)
+}
+
+/// Like [`feed`], but the enclosures are on the server at `base_url`.
+pub fn feed_at(base_url: &str, episodes: &[(&str, &str)]) -> Vec<u8> {
+    let items: String = episodes
+        .iter()
+        .enumerate()
+        .map(|(index, (title, path))| {
+            format!(
+                "<item><title>{title}</title><guid>{title}</guid>\
+                 <pubDate>{day:02} Jan 2024 10:00:00 +0000</pubDate>\
+                 <enclosure url=\"{url}\" type=\"audio/mp4\" length=\"0\"/></item>",
+                day = index + 1,
+                url = format!("{}{}", base_url, path),
+            )
+        })
+        .collect();
+
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+         <rss version=\"2.0\"><channel><title>Fixtures</title>{}</channel></rss>",
+        items
+    )
+    .into_bytes()
+}
+
+/// Points the config and cache directories to a temporary directory that's shared by all
+/// the tests, so they don't touch the ones of the user.
+fn home() -> &'static Path {
+    static HOME: OnceLock<TempDir> = OnceLock::new();
+
+    HOME.get_or_init(|| {
+        let home = TempDir::new().unwrap();
+        std::env::set_var("HOME", home.path());
+        std::env::set_var("XDG_CONFIG_HOME", home.path().join("config"));
+        std::env::set_var("XDG_CACHE_HOME", home.path().join("cache"));
+        home
+    })
+    .path()
+}
+
+/// Downloads to its own directory for every podcast, and retries without waiting.
+pub fn global_config() -> GlobalConfig {
+    global_config_with("")
+}
+
+/// The [`global_config`] with the extra settings, in TOML.
+pub fn global_config_with(settings: &str) -> GlobalConfig {
+    let config = format!(
+        "download_path = \"{}/downloads/{{podname}}\"\nretries = 2\nretry_backoff_ms = 1\n{}",
+        home().display(),
+        settings
+    );
+    toml::from_str(&config).unwrap()
+}
+
+pub fn download_dir(podcast: &str) -> PathBuf {
+    home().join("downloads").join(podcast)
+}
+
+pub struct NoProgress;
+
+impl ProgressBackend for NoProgress {}
+
+/// Fetches the feed at the path as a podcast with the given name.
+pub async fn fetch(server: &Arc<FixtureServer>, name: &str, path: &str) -> Result<Podcast, Error> {
+    fetch_with_config(server, name, path, &global_config()).await
+}
+
+pub async fn fetch_with_config(
+    server: &Arc<FixtureServer>,
+    name: &str,
+    path: &str,
+    global_config: &GlobalConfig,
+) -> Result<Podcast, Error> {
+    let client: Arc<dyn HttpClient> = server.clone();
+
+    Podcast::fetch_with_client(
+        name,
+        PodcastConfig::new(url(path)),
+        global_config,
+        FetchMode::ForceRefresh,
+        client,
+        Box::new(NoProgress),
+    )
+    .await
+}
+
+pub fn episode<'a>(podcast: &'a Podcast, title: &str) -> &'a Episode {
+    podcast
+        .episodes()
+        .iter()
+        .find(|episode| episode.title() == title)
+        .unwrap()
+}
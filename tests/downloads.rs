@@ -0,0 +1,335 @@
+mod common;
+
+use common::{FixtureServer, LoopbackServer, NoProgress, Route};
+use std::fs;
+use talecast::http::{header, StatusCode};
+use talecast::{Error, FetchMode, Podcast, PodcastConfig, Record};
+
+/// Serves a feed with a single episode, whose enclosure is at `/episode.m4a`.
+fn single_episode(server: &FixtureServer) {
+    server.serve(
+        "/feed.xml",
+        Route::file(&common::feed(&[("episode", "/episode.m4a")])),
+    );
+}
+
+fn downloaded_path(record: &Record) -> &std::path::Path {
+    record.path().expect("episode wasn't downloaded")
+}
+
+#[tokio::test]
+async fn downloads_enclosure() {
+    let server = FixtureServer::new();
+    single_episode(&server);
+    let body = common::enclosure(10_000, 1);
+    server.serve("/episode.m4a", Route::file(&body));
+
+    let podcast = common::fetch(&server, "downloads_enclosure", "/feed.xml")
+        .await
+        .unwrap();
+    let episode = common::episode(&podcast, "episode");
+    assert_eq!(podcast.pending_episodes().len(), 1);
+
+    let record = podcast
+        .download(episode, Box::new(NoProgress))
+        .await
+        .unwrap();
+
+    let path = downloaded_path(&record);
+    assert!(path.starts_with(common::download_dir("downloads_enclosure")));
+    assert_eq!(path.extension().unwrap(), "m4a");
+    assert_eq!(fs::read(path).unwrap(), body);
+    assert_eq!(
+        server.ranges("/episode.m4a"),
+        [Some("bytes=0-".to_string())]
+    );
+
+    // It's tracked as downloaded, so the next sync won't download it again.
+    let podcast = common::fetch(&server, "downloads_enclosure", "/feed.xml")
+        .await
+        .unwrap();
+    assert!(podcast.pending_episodes().is_empty());
+}
+
+#[tokio::test]
+async fn downloads_over_a_real_connection() {
+    let fixtures = FixtureServer::new();
+    let server = LoopbackServer::start(fixtures.clone()).await;
+    let feed = common::feed_at(&server.url(""), &[("episode", "/episode.m4a")]);
+    fixtures.serve("/feed.xml", Route::file(&feed));
+    let body = common::enclosure(100_000, 9);
+    fixtures.serve(
+        "/episode.m4a",
+        Route::redirect(StatusCode::FOUND, "/cdn/episode.m4a"),
+    );
+    fixtures.serve("/cdn/episode.m4a", Route::file(&body));
+
+    // Fetched with the client that's used outside of tests.
+    let global_config = common::global_config();
+    let podcast = Podcast::fetch(
+        "real_connection",
+        PodcastConfig::new(server.url("/feed.xml")),
+        &global_config,
+        FetchMode::ForceRefresh,
+        Box::new(NoProgress),
+    )
+    .await
+    .unwrap();
+    let episode = common::episode(&podcast, "episode");
+
+    let record = podcast
+        .download(episode, Box::new(NoProgress))
+        .await
+        .unwrap();
+
+    assert_eq!(fs::read(downloaded_path(&record)).unwrap(), body);
+    assert_eq!(
+        fixtures.ranges("/cdn/episode.m4a"),
+        [Some("bytes=0-".to_string())]
+    );
+    let feed_request = &fixtures.requests("/feed.xml")[0];
+    assert_eq!(
+        feed_request.headers[header::USER_AGENT],
+        global_config.user_agent().as_str()
+    );
+}
+
+#[tokio::test]
+async fn follows_enclosure_redirects() {
+    let server = FixtureServer::new();
+    single_episode(&server);
+    let body = common::enclosure(5_000, 2);
+    server.serve(
+        "/episode.m4a",
+        Route::redirect(StatusCode::FOUND, "/tracking"),
+    );
+    server.serve(
+        "/tracking",
+        Route::redirect(StatusCode::MOVED_PERMANENTLY, "/cdn/episode.m4a"),
+    );
+    server.serve("/cdn/episode.m4a", Route::file(&body));
+
+    let podcast = common::fetch(&server, "enclosure_redirects", "/feed.xml")
+        .await
+        .unwrap();
+    let episode = common::episode(&podcast, "episode");
+
+    let record = podcast
+        .download(episode, Box::new(NoProgress))
+        .await
+        .unwrap();
+
+    assert_eq!(fs::read(downloaded_path(&record)).unwrap(), body);
+    // The range survives the redirects.
+    assert_eq!(
+        server.ranges("/cdn/episode.m4a"),
+        [Some("bytes=0-".to_string())]
+    );
+}
+
+#[tokio::test]
+async fn resumes_interrupted_download() {
+    let server = FixtureServer::new();
+    single_episode(&server);
+    let body = common::enclosure(10_000, 3);
+    server.serve_once(
+        "/episode.m4a",
+        Route::Interrupted {
+            body: body.clone(),
+            after: 4_096,
+        },
+    );
+    server.serve("/episode.m4a", Route::file(&body));
+
+    let podcast = common::fetch(&server, "resumes_interrupted", "/feed.xml")
+        .await
+        .unwrap();
+    let episode = common::episode(&podcast, "episode");
+
+    let record = podcast
+        .download(episode, Box::new(NoProgress))
+        .await
+        .unwrap();
+
+    assert_eq!(fs::read(downloaded_path(&record)).unwrap(), body);
+    assert_eq!(
+        server.ranges("/episode.m4a"),
+        [
+            Some("bytes=0-".to_string()),
+            Some("bytes=4096-".to_string())
+        ]
+    );
+}
+
+#[tokio::test]
+async fn restarts_when_server_ignores_range() {
+    let server = FixtureServer::new();
+    single_episode(&server);
+    let body = common::enclosure(10_000, 4);
+    server.serve_once(
+        "/episode.m4a",
+        Route::Interrupted {
+            body: body.clone(),
+            after: 4_096,
+        },
+    );
+    server.serve(
+        "/episode.m4a",
+        Route::File {
+            body: body.clone(),
+            ranges: false,
+        },
+    );
+
+    let podcast = common::fetch(&server, "ignores_range", "/feed.xml")
+        .await
+        .unwrap();
+    let episode = common::episode(&podcast, "episode");
+
+    let record = podcast
+        .download(episode, Box::new(NoProgress))
+        .await
+        .unwrap();
+
+    // The partial file is thrown away instead of having the full body appended to it.
+    assert_eq!(fs::read(downloaded_path(&record)).unwrap(), body);
+    assert_eq!(server.ranges("/episode.m4a").len(), 2);
+}
+
+#[tokio::test]
+async fn restarts_on_unsatisfiable_range() {
+    let server = FixtureServer::new();
+    single_episode(&server);
+    let body = common::enclosure(10_000, 5);
+    server.serve_once(
+        "/episode.m4a",
+        Route::Interrupted {
+            body: body.clone(),
+            after: 4_096,
+        },
+    );
+    server.serve_once(
+        "/episode.m4a",
+        Route::Status(StatusCode::RANGE_NOT_SATISFIABLE),
+    );
+    server.serve("/episode.m4a", Route::file(&body));
+
+    let podcast = common::fetch(&server, "unsatisfiable_range", "/feed.xml")
+        .await
+        .unwrap();
+    let episode = common::episode(&podcast, "episode");
+
+    let record = podcast
+        .download(episode, Box::new(NoProgress))
+        .await
+        .unwrap();
+
+    assert_eq!(fs::read(downloaded_path(&record)).unwrap(), body);
+    assert_eq!(
+        server.ranges("/episode.m4a"),
+        [
+            Some("bytes=0-".to_string()),
+            Some("bytes=4096-".to_string()),
+            Some("bytes=0-".to_string())
+        ]
+    );
+}
+
+#[tokio::test]
+async fn failing_episode_doesnt_affect_others() {
+    let server = FixtureServer::new();
+    server.serve(
+        "/feed.xml",
+        Route::file(&common::feed(&[
+            ("missing", "/missing.m4a"),
+            ("fine", "/fine.m4a"),
+        ])),
+    );
+    let body = common::enclosure(2_000, 6);
+    server.serve("/fine.m4a", Route::file(&body));
+
+    let podcast = common::fetch(&server, "failing_episode", "/feed.xml")
+        .await
+        .unwrap();
+    let records = podcast.download_pending(Box::new(NoProgress)).await;
+
+    let [Record::EpisodeError { title, .. }, downloaded @ Record::Episode { .. }] = &records[..]
+    else {
+        panic!("expected an error and a download, got {:?}", records);
+    };
+    assert_eq!(title, "missing");
+    assert_eq!(fs::read(downloaded_path(downloaded)).unwrap(), body);
+    // A client error isn't worth retrying.
+    assert_eq!(server.requests("/missing.m4a").len(), 1);
+
+    // Only the failed episode is downloaded on the next sync.
+    let podcast = common::fetch(&server, "failing_episode", "/feed.xml")
+        .await
+        .unwrap();
+    let pending: Vec<&str> = podcast
+        .pending_episodes()
+        .iter()
+        .map(|episode| episode.title())
+        .collect();
+    assert_eq!(pending, ["missing"]);
+}
+
+#[tokio::test]
+async fn server_errors_fail_after_retries() {
+    let server = FixtureServer::new();
+    single_episode(&server);
+    server.serve(
+        "/episode.m4a",
+        Route::Status(StatusCode::INTERNAL_SERVER_ERROR),
+    );
+
+    let podcast = common::fetch(&server, "server_errors", "/feed.xml")
+        .await
+        .unwrap();
+    let episode = common::episode(&podcast, "episode");
+
+    let result = podcast.download(episode, Box::new(NoProgress)).await;
+
+    assert!(matches!(result, Err(Error::Network(_))));
+    // The first attempt and the two retries from the config.
+    assert_eq!(server.requests("/episode.m4a").len(), 3);
+}
+
+#[tokio::test]
+async fn downloading_again_replaces_symlink() {
+    let server = FixtureServer::new();
+    single_episode(&server);
+    let body = common::enclosure(3_000, 7);
+    server.serve("/episode.m4a", Route::file(&body));
+
+    let global_config = common::global_config_with("symlink = '{home}/links/{podname}'\n");
+    let podcast =
+        common::fetch_with_config(&server, "replaces_symlink", "/feed.xml", &global_config)
+            .await
+            .unwrap();
+    let episode = common::episode(&podcast, "episode");
+
+    let first = podcast
+        .download(episode, Box::new(NoProgress))
+        .await
+        .unwrap();
+    // Like with --force, which downloads the episode even though it's tracked.
+    let second = podcast
+        .download(episode, Box::new(NoProgress))
+        .await
+        .unwrap();
+
+    let symlink = |record: &Record| match record {
+        Record::Episode {
+            symlink: Some(symlink),
+            ..
+        } => symlink.clone(),
+        _ => panic!("episode wasn't symlinked"),
+    };
+    assert_eq!(symlink(&first), symlink(&second));
+    assert_eq!(
+        fs::read_link(symlink(&second)).unwrap(),
+        downloaded_path(&second)
+    );
+    assert_eq!(fs::read(symlink(&second)).unwrap(), body);
+}
@@ -0,0 +1,173 @@
+mod common;
+
+use common::{FixtureServer, Route};
+use talecast::http::StatusCode;
+use talecast::Error;
+
+#[tokio::test]
+async fn fetches_feed() {
+    let server = FixtureServer::new();
+    server.serve(
+        "/feed.xml",
+        Route::file(&common::feed(&[
+            ("first", "/first.m4a"),
+            ("second", "/second.m4a"),
+        ])),
+    );
+
+    let podcast = common::fetch(&server, "fetches_feed", "/feed.xml")
+        .await
+        .unwrap();
+
+    let titles: Vec<&str> = podcast
+        .episodes()
+        .iter()
+        .map(|episode| episode.title())
+        .collect();
+    assert_eq!(titles, ["first", "second"]);
+    assert_eq!(podcast.new_url(), None);
+}
+
+#[tokio::test]
+async fn permanent_redirect_moves_feed() {
+    let server = FixtureServer::new();
+    server.serve(
+        "/old.xml",
+        Route::redirect(StatusCode::MOVED_PERMANENTLY, "/new.xml"),
+    );
+    server.serve(
+        "/new.xml",
+        Route::file(&common::feed(&[("first", "/first.m4a")])),
+    );
+
+    let podcast = common::fetch(&server, "permanent_redirect", "/old.xml")
+        .await
+        .unwrap();
+
+    assert_eq!(podcast.episodes().len(), 1);
+    assert_eq!(podcast.new_url(), Some(common::url("/new.xml").as_str()));
+}
+
+#[tokio::test]
+async fn temporary_redirect_keeps_feed() {
+    let server = FixtureServer::new();
+    server.serve(
+        "/feed.xml",
+        Route::redirect(StatusCode::FOUND, "/today.xml"),
+    );
+    server.serve(
+        "/today.xml",
+        Route::file(&common::feed(&[("first", "/first.m4a")])),
+    );
+
+    let podcast = common::fetch(&server, "temporary_redirect", "/feed.xml")
+        .await
+        .unwrap();
+
+    assert_eq!(podcast.episodes().len(), 1);
+    assert_eq!(podcast.new_url(), None);
+}
+
+#[tokio::test]
+async fn temporary_redirect_in_chain_keeps_feed() {
+    let server = FixtureServer::new();
+    server.serve(
+        "/old.xml",
+        Route::redirect(StatusCode::PERMANENT_REDIRECT, "/new.xml"),
+    );
+    server.serve(
+        "/new.xml",
+        Route::redirect(StatusCode::TEMPORARY_REDIRECT, "/cdn.xml"),
+    );
+    server.serve(
+        "/cdn.xml",
+        Route::file(&common::feed(&[("first", "/first.m4a")])),
+    );
+
+    let podcast = common::fetch(&server, "redirect_chain", "/old.xml")
+        .await
+        .unwrap();
+
+    // Only the permanent part of the chain is where the feed moved to.
+    assert_eq!(podcast.new_url(), Some(common::url("/new.xml").as_str()));
+}
+
+#[tokio::test]
+async fn redirect_loop_fails() {
+    let server = FixtureServer::new();
+    server.serve("/a.xml", Route::redirect(StatusCode::FOUND, "/b.xml"));
+    server.serve("/b.xml", Route::redirect(StatusCode::FOUND, "/a.xml"));
+
+    let result = common::fetch(&server, "redirect_loop", "/a.xml").await;
+
+    assert!(matches!(result, Err(Error::Network(_))));
+    // Too many redirects isn't worth retrying.
+    assert_eq!(server.requests("/a.xml").len(), 6);
+}
+
+#[tokio::test]
+async fn missing_feed_fails() {
+    let server = FixtureServer::new();
+
+    let result = common::fetch(&server, "missing_feed", "/feed.xml").await;
+
+    assert!(matches!(result, Err(Error::Network(_))));
+    assert_eq!(server.requests("/feed.xml").len(), 1);
+}
+
+#[tokio::test]
+async fn server_error_is_retried() {
+    let server = FixtureServer::new();
+    server.serve_once("/feed.xml", Route::Status(StatusCode::SERVICE_UNAVAILABLE));
+    server.serve(
+        "/feed.xml",
+        Route::file(&common::feed(&[("first", "/first.m4a")])),
+    );
+
+    let podcast = common::fetch(&server, "feed_server_error", "/feed.xml")
+        .await
+        .unwrap();
+
+    assert_eq!(podcast.episodes().len(), 1);
+    assert_eq!(server.requests("/feed.xml").len(), 2);
+}
+
+#[tokio::test]
+async fn listing_normalizes_durations() {
+    let item = |title: &str, duration: &str| {
+        format!(
+            "<item><title>{title}</title><guid>{title}</guid>{duration}\
+             <pubDate>01 Jan 2024 10:00:00 +0000</pubDate>\
+             <enclosure url=\"{}\" type=\"audio/mp4\" length=\"0\"/></item>",
+            common::url(&format!("/{title}.m4a"))
+        )
+    };
+    let feed = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+         <rss version=\"2.0\"><channel><title>Fixtures</title>{}{}{}</channel></rss>",
+        item("seconds", "<itunes:duration>3723</itunes:duration>"),
+        item("minutes", "<itunes:duration>62:03</itunes:duration>"),
+        item("none", ""),
+    );
+    let server = FixtureServer::new();
+    server.serve("/feed.xml", Route::file(feed.as_bytes()));
+
+    let podcast = common::fetch(&server, "listing_durations", "/feed.xml")
+        .await
+        .unwrap();
+
+    let mut durations: Vec<(String, Option<String>)> = podcast
+        .listings()
+        .into_iter()
+        .map(|listing| (listing.title, listing.duration))
+        .collect();
+    durations.sort();
+    assert_eq!(
+        durations,
+        [
+            ("minutes".to_string(), Some("01:02:03".to_string())),
+            ("none".to_string(), None),
+            ("seconds".to_string(), Some("01:02:03".to_string())),
+        ]
+    );
+}
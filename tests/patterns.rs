@@ -0,0 +1,97 @@
+mod common;
+
+use common::{FixtureServer, NoProgress, Route};
+use talecast::{Error, Podcast, Record};
+
+const FEED: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0" xmlns:itunes="http://www.itunes.com/dtds/podcast-1.0.dtd">
+<channel>
+<title>Fixtures</title>
+<item>
+  <title>Hello, World! Part 2</title>
+  <guid>g1</guid>
+  <pubDate>01 Jan 2024 10:00:00 +0000</pubDate>
+  <enclosure url="http://fixtures.test/episode.m4a" type="audio/mp4" length="0"/>
+  <itunes:episode>7</itunes:episode>
+</item>
+<item>
+  <title>Bonus</title>
+  <guid>g2</guid>
+  <pubDate>02 Jan 2024 10:00:00 +0000</pubDate>
+  <enclosure url="http://fixtures.test/bonus.m4a" type="audio/mp4" length="0"/>
+</item>
+</channel>
+</rss>"#;
+
+/// Fetches the fixture feed with the extra global settings, in TOML.
+async fn fetch(podcast: &str, settings: &str) -> Result<Podcast, Error> {
+    let server = FixtureServer::new();
+    server.serve("/feed.xml", Route::file(FEED.as_bytes()));
+
+    let global_config = common::global_config_with(settings);
+    common::fetch_with_config(&server, podcast, "/feed.xml", &global_config).await
+}
+
+#[tokio::test]
+async fn name_pattern_is_evaluated() {
+    let settings = "name_pattern = '{podname | slugify}-{episode | pad:3}.{ext}'";
+    let podcast = fetch("Name Pattern", settings).await.unwrap();
+
+    assert_eq!(podcast.episodes()[0].file_name(), "name-pattern-007.m4a");
+}
+
+#[tokio::test]
+async fn download_path_is_evaluated() {
+    let podcast = fetch("Download Path", "").await.unwrap();
+
+    assert_eq!(
+        podcast.episodes()[0].download_dir(),
+        common::download_dir("Download Path")
+    );
+}
+
+#[tokio::test]
+async fn tracker_path_is_evaluated() {
+    let settings = "tracker_path = '{home}/trackers/{podname | slugify}'";
+    let podcast = fetch("Tracker Path", settings).await.unwrap();
+
+    assert!(podcast.episodes()[0]
+        .tracker_path()
+        .ends_with("trackers/tracker-path"));
+}
+
+#[tokio::test]
+async fn symlink_is_evaluated() {
+    let settings = r#"symlink = '{home}/links/{podname | replace:" ":""}'"#;
+    let podcast = fetch("Symlink Path", settings).await.unwrap();
+
+    assert!(podcast.episodes()[0]
+        .symlink_dir()
+        .unwrap()
+        .ends_with("links/SymlinkPath"));
+}
+
+#[tokio::test]
+async fn strict_patterns_only_fail_the_episode() {
+    let server = FixtureServer::new();
+    server.serve("/feed.xml", Route::file(FEED.as_bytes()));
+    server.serve("/episode.m4a", Route::file(&common::enclosure(1_000, 1)));
+
+    let global_config =
+        common::global_config_with("name_pattern = '{episode}'\nstrict_patterns = true");
+    let podcast = common::fetch_with_config(&server, "strict_missing", "/feed.xml", &global_config)
+        .await
+        .unwrap();
+    assert_eq!(podcast.episodes().len(), 1);
+
+    let records = podcast.download_pending(Box::new(NoProgress)).await;
+
+    assert_eq!(records.len(), 2);
+    assert!(records.iter().any(|record| matches!(
+        record,
+        Record::EpisodeError { guid, error, .. } if guid == "g2" && error.contains("{episode}")
+    )));
+    assert!(records
+        .iter()
+        .any(|record| matches!(record, Record::Episode { guid, .. } if guid == "g1")));
+}